@@ -0,0 +1,624 @@
+//! Provides functions to inspect the structure of the computational graph.
+
+use crate::node::{Node, generation};
+use crate::operations::Input;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+/// Returns all the nodes reachable from `root` (including itself) in the depth-first pre-order.
+/// The shared nodes are listed only once, at the place of their first occurrence.
+pub fn nodes_of<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<Rc<dyn Node<Output = T>>> {
+    let mut visited = HashSet::new();
+    let mut res = Vec::new();
+    let mut stack = vec![root.clone()];
+    while let Some(n) = stack.pop() {
+        if !visited.insert(n.id()) {
+            continue;
+        }
+        // Children are pushed in the reversed order, so the first child is visited first.
+        stack.extend(n.children().into_iter().rev());
+        res.push(n);
+    }
+    res
+}
+
+/// Returns all the input nodes reachable from `root` in the depth-first pre-order.
+pub fn inputs_of<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<Rc<dyn Node<Output = T>>> {
+    nodes_of(root).into_iter().filter(|n| n.is_input()).collect()
+}
+
+/// Returns `(child id, parent id)` of every dependency edge of the graph reachable from `root`,
+/// in the depth-first pre-order of the parents. An input used twice by the same node gives a single edge.
+pub fn edges<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<(usize, usize)> {
+    let mut seen = HashSet::new();
+    nodes_of(root).iter()
+        .flat_map(|n| n.children().into_iter().map(|c| (c.id(), n.id())).collect::<Vec<_>>())
+        .filter(|&e| seen.insert(e))
+        .collect()
+}
+
+/// Returns `true` if every node reachable from `root` is reachable by a single path, i.e. the graph is a tree
+/// without the shared nodes. The node used twice as an input of the same operation is shared as well.
+pub fn is_tree<T>(root: &Rc<dyn Node<Output = T>>) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![root.clone()];
+    while let Some(n) = stack.pop() {
+        if !visited.insert(n.id()) {
+            return false;
+        }
+        stack.extend(n.children());
+    }
+    true
+}
+
+/// Error of [`validate_inputs`] listing the names of the inputs that differ from the expected ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMismatch {
+    /// Expected names that are not referenced by the graph.
+    pub missing: Vec<String>,
+    /// Names referenced by the graph that are not expected.
+    pub unexpected: Vec<String>
+}
+
+impl fmt::Display for InputMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "graph inputs do not match the expected ones")?;
+        if !self.missing.is_empty() {
+            write!(f, ", missing: {}", self.missing.join(", "))?;
+        }
+        if !self.unexpected.is_empty() {
+            write!(f, ", unexpected: {}", self.unexpected.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InputMismatch {}
+
+/// Checks that the graph references exactly the inputs with the `expected` names, no more and no fewer.
+pub fn validate_inputs<T>(root: &Rc<dyn Node<Output = T>>, expected: &[&str]) -> Result<(), InputMismatch> {
+    let names: BTreeSet<String> = inputs_of(root).iter().map(|n| n.label()).collect();
+    let expected: BTreeSet<String> = expected.iter().map(|s| s.to_string()).collect();
+    let missing: Vec<String> = expected.difference(&names).cloned().collect();
+    let unexpected: Vec<String> = names.difference(&expected).cloned().collect();
+    if missing.is_empty() && unexpected.is_empty() {
+        Ok(())
+    } else {
+        Err(InputMismatch { missing, unexpected })
+    }
+}
+
+/// Compares the graph structure of `a` and `b` with the operand order given by `commutative`.
+fn structural_eq_by<T: PartialEq>(
+    a: &Rc<dyn Node<Output = T>>,
+    b: &Rc<dyn Node<Output = T>>,
+    commutative: &dyn Fn(&str) -> bool
+) -> bool {
+    if a.id() == b.id() {
+        return true;
+    }
+    if a.is_input() || b.is_input() || a.label() != b.label() || a.constant_value() != b.constant_value() {
+        return false;
+    }
+    let (ca, cb) = (a.children(), b.children());
+    if ca.len() != cb.len() {
+        return false;
+    }
+    let eq = |x: usize, y: usize| structural_eq_by(&ca[x], &cb[y], commutative);
+    (0..ca.len()).all(|i| eq(i, i)) || (ca.len() == 2 && commutative(&a.label()) && eq(0, 1) && eq(1, 0))
+}
+
+/// Checks whether two graphs have the same structure: the same operations with the same constants
+/// on the same input nodes. Inputs are compared by identity, not by name.
+pub fn structural_eq<T: PartialEq>(a: &Rc<dyn Node<Output = T>>, b: &Rc<dyn Node<Output = T>>) -> bool {
+    structural_eq_by(a, b, &|_| false)
+}
+
+/// Checks whether two graphs have the same structure like [`structural_eq`], but treats the operands
+/// of the commutative operations `add` and `mul` as unordered, so `add(x, y)` equals `add(y, x)`.
+pub fn structural_eq_commutative<T: PartialEq>(a: &Rc<dyn Node<Output = T>>, b: &Rc<dyn Node<Output = T>>) -> bool {
+    structural_eq_by(a, b, &|label| label == "add" || label == "mul")
+}
+
+/// Returns `true` if the value of `root` is an affine function of the inputs, i.e. every node reachable
+/// from it is an input, a constant, `add`, `sub`, `neg`, or `mul` or `div` by a constant node.
+pub fn is_affine<T>(root: &Rc<dyn Node<Output = T>>) -> bool {
+    nodes_of(root).iter().all(|n| {
+        if n.is_input() || n.constant_value().is_some() {
+            return true;
+        }
+        let is_const = |c: &Rc<dyn Node<Output = T>>| c.constant_value().is_some();
+        match (n.label().as_str(), n.children().as_slice()) {
+            ("add" | "sub", [_, _]) | ("neg", [_]) => true,
+            ("mul", [x, y]) => is_const(x) || is_const(y),
+            ("div", [_, y]) => is_const(y),
+            _ => false
+        }
+    })
+}
+
+/// Returns the partial derivatives of the node with respect to each of its inputs at their current values,
+/// given by the operation of the node (see [`Node::derivative`] and [`Node::partials`]), not by its label.
+/// The partial derivatives of the other operations are unknown and returned as NaN.
+fn local_grads(n: &Rc<dyn Node<Output = f32>>) -> Vec<f32> {
+    let xs: Vec<f32> = n.children().iter().map(|c| c.compute()).collect();
+    match xs.as_slice() {
+        [x] => vec![n.derivative().map_or(f32::NAN, |d| d(*x))],
+        [x, y] => n.partials().map_or(vec![f32::NAN; 2], |p| p(*x, *y).to_vec()),
+        _ => vec![f32::NAN; xs.len()]
+    }
+}
+
+/// Returns the gradient of `output` with respect to every input reachable from it, keyed by the input id,
+/// computed by a single reverse pass over the graph. The inputs used by several nodes accumulate
+/// the gradients of all the paths. The gradient through the operations with unknown derivatives is NaN.
+pub fn backward_all(output: &Rc<dyn Node<Output = f32>>) -> HashMap<usize, f32> {
+    let mut adjoints: HashMap<usize, f32> = HashMap::from([(output.id(), 1.0)]);
+    let mut res = HashMap::new();
+    for n in topological_order(output).iter().rev() {
+        let adjoint = adjoints.get(&n.id()).copied().unwrap_or(0.0);
+        if n.is_input() {
+            res.insert(n.id(), adjoint);
+            continue;
+        }
+        for (c, g) in n.children().iter().zip(local_grads(n)) {
+            *adjoints.entry(c.id()).or_default() += adjoint * g;
+        }
+    }
+    res
+}
+
+/// Returns the numeric derivative `d output / d input` at the current value of the input, estimated by the central
+/// difference. The input is set to the shifted values and then restored, so the stateful nodes see the extra
+/// recomputations.
+pub fn numeric_grad(output: &Rc<dyn Node<Output = f32>>, input: &Rc<Input>) -> f32 {
+    let x = input.compute();
+    let h = 1e-3 * x.abs().max(1.0);
+    input.set(x + h);
+    let hi = output.compute();
+    input.set(x - h);
+    let lo = output.compute();
+    input.set(x);
+    (hi - lo) / (2.0 * h)
+}
+
+/// Returns the elasticity `(d output / d input) * (input / output)` of the output with respect to the input,
+/// that is the dimensionless sensitivity at the current values. Returns NaN when the output is zero.
+pub fn elasticity(output: &Rc<dyn Node<Output = f32>>, input: &Rc<Input>) -> f32 {
+    let y = output.compute();
+    if y == 0.0 {
+        return f32::NAN;
+    }
+    numeric_grad(output, input) * input.compute() / y
+}
+
+/// Returns the value of `root` and its age: the number of the input generations (see [`generation`]) since
+/// its last recomputation. The age is zero if the value is recomputed by this call or the node has no cache.
+pub fn compute_with_age<T>(root: &Rc<dyn Node<Output = T>>) -> (T, u64) {
+    let value = root.compute();
+    let age = root.computed_at().map_or(0, |g| generation() - g);
+    (value, age)
+}
+
+/// Returns all the nodes reachable from `root` in the topological order: every node goes after all its inputs,
+/// so `root` is the last one. The shared nodes are listed only once.
+pub fn topological_order<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<Rc<dyn Node<Output = T>>> {
+    let mut visited = HashSet::new();
+    let mut res = Vec::new();
+    // The node is pushed with `false` to expand its inputs and then with `true` to be added after them.
+    let mut stack = vec![(root.clone(), false)];
+    while let Some((n, expanded)) = stack.pop() {
+        if expanded {
+            res.push(n);
+            continue;
+        }
+        if !visited.insert(n.id()) {
+            continue;
+        }
+        let children = n.children();
+        stack.push((n, true));
+        stack.extend(children.into_iter().rev().map(|c| (c, false)));
+    }
+    res
+}
+
+/// Warning of [`compute_checked`] listing the nodes with the values that are subnormal, infinite or NaN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericWarning {
+    /// Ids of the offending nodes in the topological order.
+    pub nodes: Vec<usize>,
+    /// Value of the root, that may be finite despite the offending intermediate values.
+    pub value: f32
+}
+
+impl fmt::Display for NumericWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<String> = self.nodes.iter().map(|id| id.to_string()).collect();
+        write!(f, "subnormal or non-finite values at nodes: {}", ids.join(", "))
+    }
+}
+
+impl Error for NumericWarning {}
+
+/// Computes the nodes reachable from `root` one by one in the topological order and returns the value of `root`,
+/// or the warning if any of the values, including the intermediate ones, is subnormal, infinite or NaN.
+pub fn compute_checked(root: &Rc<dyn Node<Output = f32>>) -> Result<f32, NumericWarning> {
+    let mut nodes = Vec::new();
+    let mut value = 0.0;
+    for (id, v) in eval_steps(root) {
+        if v.is_subnormal() || !v.is_finite() {
+            nodes.push(id);
+        }
+        value = v;
+    }
+    if nodes.is_empty() {
+        Ok(value)
+    } else {
+        Err(NumericWarning { nodes, value })
+    }
+}
+
+/// Returns the ids of the up to date nodes of `order`. The caching node is up to date if it stores the value,
+/// the other nodes, e.g. inputs or nodes with disabled caching, if all their inputs are up to date.
+fn clean_nodes<T>(order: &[Rc<dyn Node<Output = T>>]) -> HashSet<usize> {
+    let mut clean = HashSet::new();
+    for n in order {
+        let up_to_date = if n.is_caching() {
+            n.is_cached()
+        } else {
+            n.children().iter().all(|c| clean.contains(&c.id()))
+        };
+        if up_to_date {
+            clean.insert(n.id());
+        }
+    }
+    clean
+}
+
+/// Refreshes at most `budget` of the invalidated operations reachable from `root`, and returns the value of `root`
+/// if it is up to date afterwards. An operation is refreshed only after its inputs, among the operations ready
+/// to refresh the one with the highest priority (see [`Node::set_priority`]) goes first.
+/// The nodes with disabled caching are not counted as refreshed, they are computed with the value of `root`.
+pub fn compute_budgeted<T>(root: &Rc<dyn Node<Output = T>>, budget: usize) -> Option<T> {
+    let order = topological_order(root);
+    for _ in 0..budget {
+        let clean = clean_nodes(&order);
+        let ready = order.iter()
+            .filter(|n| n.is_caching() && !clean.contains(&n.id()))
+            .filter(|n| n.children().iter().all(|c| clean.contains(&c.id())))
+            // The first of the nodes with the same priority is taken, i.e. the one earlier in the topological order.
+            .rev()
+            .max_by_key(|n| n.priority());
+        match ready {
+            Some(n) => { n.compute(); }
+            None => break
+        }
+    }
+    clean_nodes(&order).contains(&root.id()).then(|| root.compute())
+}
+
+/// Returns the ids of the nodes along the path from `root` to an input, that is chosen by the path length
+/// with `better`. Returns the empty path if no input is reachable from `root`.
+fn path_by<T>(root: &Rc<dyn Node<Output = T>>, better: fn(usize, usize) -> bool) -> Vec<usize> {
+    // The length of the chosen path from the node to an input and the next node of the path.
+    let mut paths: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
+    for n in topological_order(root) {
+        if n.is_input() {
+            paths.insert(n.id(), (0, None));
+            continue;
+        }
+        let best = n.children().iter()
+            .filter_map(|c| paths.get(&c.id()).map(|&(len, _)| (len + 1, c.id())))
+            .reduce(|a, b| if better(b.0, a.0) { b } else { a });
+        if let Some((len, next)) = best {
+            paths.insert(n.id(), (len, Some(next)));
+        }
+    }
+    let mut res = Vec::new();
+    let mut current = paths.contains_key(&root.id()).then(|| root.id());
+    while let Some(id) = current {
+        res.push(id);
+        current = paths[&id].1;
+    }
+    res
+}
+
+/// Returns the ids of the nodes along the longest path from `root` to an input, starting with `root`.
+/// It is the longest chain of the operations to recompute after the change of an input.
+pub fn critical_path<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<usize> {
+    path_by(root, |a, b| a > b)
+}
+
+/// Returns the ids of the nodes along the shortest path from `root` to an input, starting with `root`.
+pub fn shortest_path<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<usize> {
+    path_by(root, |a, b| a < b)
+}
+
+/// Returns an iterator over `(node id, value)` of all the nodes reachable from `root` in the topological order.
+/// Nodes are computed lazily one by one as the iterator advances, so the last item is the value of `root`.
+pub fn eval_steps<T>(root: &Rc<dyn Node<Output = T>>) -> impl Iterator<Item = (usize, T)> {
+    topological_order(root).into_iter().map(|n| (n.id(), n.compute()))
+}
+
+/// Returns `(label, value)` of every node reachable from `root` in the topological order,
+/// computing the values that are not cached yet. It is a flat record suitable for logging of the graph state.
+pub fn snapshot_values<T>(root: &Rc<dyn Node<Output = T>>) -> Vec<(String, T)> {
+    topological_order(root).iter().map(|n| (n.label(), n.compute())).collect()
+}
+
+/// Returns the graph as an indented tree, one node label per line with two spaces of indentation per level.
+/// The nodes reachable by several paths are marked with their id (`label #id`) and their inputs
+/// are printed only at the first occurrence.
+pub fn print_tree<T>(root: &Rc<dyn Node<Output = T>>) -> String {
+    // Count the number of the links to each node to find the shared nodes.
+    let mut links: HashMap<usize, usize> = HashMap::new();
+    for n in nodes_of(root) {
+        for c in n.children() {
+            *links.entry(c.id()).or_default() += 1;
+        }
+    }
+    let mut res = String::new();
+    let mut printed = HashSet::new();
+    let mut stack = vec![(root.clone(), 0)];
+    while let Some((n, depth)) = stack.pop() {
+        let shared = links.get(&n.id()).is_some_and(|&l| l > 1);
+        if shared {
+            writeln!(res, "{:indent$}{} #{}", "", n.label(), n.id(), indent = 2 * depth).unwrap();
+        } else {
+            writeln!(res, "{:indent$}{}", "", n.label(), indent = 2 * depth).unwrap();
+        }
+        if printed.insert(n.id()) {
+            stack.extend(n.children().into_iter().rev().map(|c| (c, depth + 1)));
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::readme_graph;
+    use crate::*;
+    use crate::operations::{Binary, Unary};
+
+    #[test]
+    fn test_nodes_of_shared() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let graph = add(x1.clone(), mul(x2.clone(), sin(x2.clone())));
+        let nodes = nodes_of(&graph);
+        assert_eq!(nodes.len(), 5);
+        assert!(Rc::ptr_eq(&nodes[0], &graph));
+        assert_eq!(nodes[1].compute(), x1.compute());
+    }
+
+    #[test]
+    fn test_print_tree() {
+        let (graph, [_, x2, _]) = readme_graph();
+        let x2 = format!("x2 #{}", x2.id());
+        let expected = [
+            "add", "  x1", "  mul", &format!("    {}", x2), "    sin", "      add",
+            &format!("        {}", x2), "        pow", "          x3", "          3",
+        ];
+        assert_eq!(print_tree(&graph), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_eval_steps() {
+        let (graph, [x1, x2, _]) = readme_graph();
+        let steps: Vec<_> = eval_steps(&graph).collect();
+        assert_eq!(steps.len(), 9);
+        assert_eq!(steps[0], (x1.id(), 1.0));
+        assert_eq!(steps[1], (x2.id(), 2.0));
+        assert_eq!(steps[4].1, 27.0);
+        assert_eq!(steps[5].1, 29.0);
+        assert_eq!(*steps.last().unwrap(), (graph.id(), graph.compute()));
+    }
+
+    #[test]
+    fn test_validate_inputs() {
+        let (graph, _) = readme_graph();
+        assert_eq!(inputs_of(&graph).len(), 3);
+        assert_eq!(validate_inputs(&graph, &["x1", "x2", "x3"]), Ok(()));
+        let err = validate_inputs(&graph, &["x1", "x2", "x4"]).unwrap_err();
+        assert_eq!(err.missing, vec!["x4"]);
+        assert_eq!(err.unexpected, vec!["x3"]);
+        assert_eq!(err.to_string(), "graph inputs do not match the expected ones, missing: x4, unexpected: x3");
+    }
+
+    #[test]
+    fn test_snapshot_values() {
+        let (graph, _) = readme_graph();
+        let record = snapshot_values(&graph);
+        let find = |label: &str| record.iter().filter(|(l, _)| l == label).map(|(_, v)| *v).collect::<Vec<_>>();
+        assert_eq!(record.len(), 9);
+        assert_eq!(find("x1"), vec![1.0]);
+        assert_eq!(find("x2"), vec![2.0]);
+        assert_eq!(find("x3"), vec![3.0]);
+        assert_eq!(find("pow"), vec![27.0]);
+        assert_eq!(find("add"), vec![29.0, graph.compute()]);
+        assert_eq!(find("sin"), vec![29f32.sin()]);
+    }
+
+    #[test]
+    fn test_structural_eq() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let a = mul(sin(x1.clone()), add(x1.clone(), constant(2.0)));
+        assert!(structural_eq(&a, &mul(sin(x1.clone()), add(x1.clone(), constant(2.0)))));
+        assert!(!structural_eq(&a, &mul(sin(x1.clone()), add(x1.clone(), constant(3.0)))));
+        assert!(!structural_eq(&a, &mul(sin(x2.clone()), add(x1.clone(), constant(2.0)))));
+        assert!(!structural_eq(&sin(x1.clone()), &sin(create_input("x1"))));
+    }
+
+    #[test]
+    fn test_structural_eq_commutative() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        assert!(!structural_eq(&add(x1.clone(), x2.clone()), &add(x2.clone(), x1.clone())));
+        assert!(structural_eq_commutative(&add(x1.clone(), x2.clone()), &add(x2.clone(), x1.clone())));
+        assert!(structural_eq_commutative(
+            &mul(sin(x1.clone()), add(x1.clone(), x2.clone())),
+            &mul(add(x2.clone(), x1.clone()), sin(x1.clone()))
+        ));
+        assert!(!structural_eq_commutative(&sub(x1.clone(), x2.clone()), &sub(x2.clone(), x1.clone())));
+    }
+
+    #[test]
+    fn test_is_affine() {
+        let x = create_input("x");
+        let y = create_input("y");
+        let graph = sub(add(mul(constant(2.0), x.clone()), mul(y.clone(), constant(-3.0))), constant(1.0));
+        assert!(is_affine(&graph));
+        assert!(!is_affine(&add(graph.clone(), sin(x.clone()))));
+        assert!(!is_affine(&mul(x.clone(), y.clone())));
+        let custom: Rc<dyn Node<Output = f32>> = Unary::with_derivative("mul", x.clone(), |x| 2.0 * x, |_| 2.0);
+        assert!(!is_affine(&custom));
+    }
+
+    #[test]
+    fn test_elasticity() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let graph = mul(x1.clone(), x2.clone());
+        x1.set(3.0);
+        x2.set(-0.5);
+        assert!((elasticity(&graph, &x1) - 1.0).abs() < 1e-3);
+        assert!((elasticity(&graph, &x2) - 1.0).abs() < 1e-3);
+        assert_eq!(graph.compute(), -1.5);
+        x2.set(0.0);
+        assert!(elasticity(&graph, &x1).is_nan());
+    }
+
+    #[test]
+    fn test_compute_with_age() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let a = sin(x1.clone());
+        let b = sin(x2.clone());
+        x1.set(1.0);
+        assert_eq!(compute_with_age(&a).1, 0);
+        assert_eq!(compute_with_age(&b).1, 0);
+        x2.set(2.0);
+        x2.set(3.0);
+        assert_eq!(compute_with_age(&a), (1f32.sin(), 2));
+        assert_eq!(compute_with_age(&b), (3f32.sin(), 0));
+        assert_eq!(compute_with_age(&b).1, 0);
+    }
+
+    #[test]
+    fn test_edges() {
+        let (graph, [x1, x2, x3]) = readme_graph();
+        let e = edges(&graph);
+        // add, mul, add and pow have two inputs each, sin has one
+        assert_eq!(e.len(), 9);
+        for x in [x1.id(), x2.id(), x3.id()] {
+            assert!(e.iter().any(|&(c, _)| c == x));
+        }
+        assert!(e.contains(&(x1.id(), graph.id())));
+        let s = sin(x1.clone());
+        assert_eq!(edges(&mul(s.clone(), s.clone())).len(), 2);
+    }
+
+    #[test]
+    fn test_compute_checked() {
+        let x = create_input("x");
+        let overflow = mul(x.clone(), x.clone());
+        let graph = mul(overflow.clone(), constant(0.0));
+        x.set(2.0);
+        assert_eq!(compute_checked(&graph), Ok(0.0));
+        x.set(1e30);
+        let warning = compute_checked(&graph).unwrap_err();
+        assert_eq!(warning.nodes[0], overflow.id());
+        // the overflow is reported even if the root value is finite
+        let graph = safe_recip(overflow.clone(), 1e-3);
+        assert_eq!(compute_checked(&graph), Err(NumericWarning { nodes: vec![overflow.id()], value: 0.0 }));
+    }
+
+    #[test]
+    fn test_compute_budgeted() {
+        let x = create_input("x");
+        let (a, b) = (sin(x.clone()), mul(x.clone(), x.clone()));
+        let graph = add(a.clone(), b.clone());
+        x.set(2.0);
+        assert_eq!(compute_budgeted(&graph, 1), None);
+        assert!(a.is_cached() && !b.is_cached());
+        assert_eq!(compute_budgeted(&graph, 2), Some(2f32.sin() + 4.0));
+
+        b.set_priority(10);
+        x.set(3.0);
+        assert_eq!(compute_budgeted(&graph, 1), None);
+        assert!(b.is_cached() && !a.is_cached());
+        assert_eq!(compute_budgeted(&graph, 2), Some(3f32.sin() + 9.0));
+    }
+
+    #[test]
+    fn test_compute_budgeted_uncached() {
+        let x = create_input("x");
+        let s = sin(x.clone()).without_cache();
+        let graph = add(s.clone(), x.clone());
+        x.set(1.0);
+        assert_eq!(compute_budgeted(&graph, 1), Some(1f32.sin() + 1.0));
+        x.set(2.0);
+        assert_eq!(compute_budgeted(&graph, 100), Some(2f32.sin() + 2.0));
+        let graph = mul(add(s.clone(), constant(1.0)), x.clone());
+        x.set(3.0);
+        assert_eq!(compute_budgeted(&graph, 1), None);
+        assert_eq!(compute_budgeted(&graph, 2), Some((3f32.sin() + 1.0) * 3.0));
+        assert_eq!(s.recompute_count(), 3);
+    }
+
+    #[test]
+    fn test_is_tree() {
+        let (graph, [x1, x2, x3]) = readme_graph();
+        assert!(!is_tree(&graph));
+        let graph = add(x1.clone(), mul(x2.clone(), sin(pow_f32(x3.clone(), 3f32))));
+        assert!(is_tree(&graph));
+        assert!(!is_tree(&mul(x1.clone(), x1.clone())));
+    }
+
+    #[test]
+    fn test_backward_all() {
+        let (x1, x2, x3) = (create_input("x1"), create_input("x2"), create_input("x3"));
+        let graph = add(mul(x1.clone(), x2.clone()), x3.clone());
+        x1.set(2.0);
+        x2.set(-3.0);
+        x3.set(5.0);
+        let grads = backward_all(&graph);
+        assert_eq!(grads.len(), 3);
+        assert_eq!(grads[&x1.id()], -3.0);
+        assert_eq!(grads[&x2.id()], 2.0);
+        assert_eq!(grads[&x3.id()], 1.0);
+
+        let graph = mul(sin(x1.clone()), x1.clone());
+        let grads = backward_all(&graph);
+        assert_eq!(grads[&x1.id()], 2f32.cos() * 2.0 + 2f32.sin());
+        assert!((grads[&x1.id()] - numeric_grad(&graph, &x1)).abs() < 1e-2);
+
+        // the custom operation with the built-in label does not know its partial derivatives
+        let custom: Rc<dyn Node<Output = f32>> = Binary::named("add", x1.clone(), x2.clone(), |x, y| x * y);
+        let grads = backward_all(&add(custom, x3.clone()));
+        assert!(grads[&x1.id()].is_nan() && grads[&x2.id()].is_nan());
+        assert_eq!(grads[&x3.id()], 1.0);
+        let grads = backward_all(&pow(x1.clone(), x2.clone()));
+        assert_eq!(grads[&x1.id()], -3.0 * 2f32.powf(-4.0));
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let x3 = create_input("x3");
+        let p = pow_f32(x3.clone(), 3f32);
+        let inner = add(x2.clone(), p.clone());
+        let s = sin(inner.clone());
+        let m = mul(x2.clone(), s.clone());
+        let graph = add(x1.clone(), m.clone());
+        assert_eq!(critical_path(&graph), vec![graph.id(), m.id(), s.id(), inner.id(), p.id(), x3.id()]);
+        assert_eq!(shortest_path(&graph), vec![graph.id(), x1.id()]);
+        assert!(critical_path(&sin(constant(1.0))).is_empty());
+    }
+}
@@ -1,26 +1,171 @@
 //! Composable cache type provides caching in the graph nodes.
-use std::{cell::RefCell};
+use crate::node::generation;
 
-/// Cache is a simple abstraction that store Copy type, that allow one to get previously computed value.
+use std::cell::{Cell, RefCell};
+
+/// Cache is a simple abstraction that store Clone type (usually Copy), that allow one to get previously computed value.
 /// If Cache is already set and valid, then it returns stored value,
-/// otherwise it compute new value form provided Fn.
+/// otherwise it compute new value form provided Fn. Cache counts the number of such recomputations
+/// and the number of the requests served with the stored value.
+/// Disabled cache does not store the values, so every request leads to recomputation.
+/// With `debug_verify` feature every `VERIFY_PERIOD`-th returned stored value is checked against
+/// the recomputed one, to catch the missing invalidations.
 #[derive(Default)]
 pub struct Cache<T> {
-    val: RefCell<Option<T>>
+    val: RefCell<Option<T>>,
+    misses: Cell<u64>,
+    disabled: Cell<bool>,
+    hits: Cell<u64>,
+    computed_at: Cell<u64>,
+    priority: Cell<u8>,
+    #[cfg(feature = "debug_verify")]
+    verified: bool
+}
+
+/// Period of the verification of the stored values with `debug_verify` feature.
+#[cfg(feature = "debug_verify")]
+pub const VERIFY_PERIOD: u64 = 4;
+
+/// Check that the stored value matches the recomputed one within the tolerance.
+/// Only `f32` and `f64` values are checked.
+#[cfg(feature = "debug_verify")]
+fn verify<T: 'static>(stored: &T, fresh: &T) {
+    use std::any::Any;
+    let pair = match ((stored as &dyn Any).downcast_ref::<f32>(), (fresh as &dyn Any).downcast_ref::<f32>()) {
+        (Some(&a), Some(&b)) => Some((a as f64, b as f64)),
+        _ => (stored as &dyn Any).downcast_ref::<f64>().zip((fresh as &dyn Any).downcast_ref::<f64>())
+            .map(|(&a, &b)| (a, b))
+    };
+    if let Some((a, b)) = pair {
+        let same = a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= 1e-5 * a.abs().max(1.0);
+        assert!(same, "cached value {} does not match recomputed value {}: missing invalidation", a, b);
+    }
 }
 
-impl<T: Copy> Cache<T> {
+impl<T: Clone> Cache<T> {
     pub(crate) fn new() -> Self {
-        Self { val: RefCell::new(None) }
+        Self {
+            val: RefCell::new(None),
+            misses: Cell::new(0),
+            disabled: Cell::new(false),
+            hits: Cell::new(0),
+            computed_at: Cell::new(0),
+            priority: Cell::new(0),
+            #[cfg(feature = "debug_verify")]
+            verified: true
+        }
     }
 
+    /// Disable the verification of the stored values, e.g. for the stateful nodes whose recomputation changes the state.
+    pub(crate) fn unverified(self) -> Self {
+        #[cfg(feature = "debug_verify")]
+        return Self { verified: false, ..self };
+        #[cfg(not(feature = "debug_verify"))]
+        self
+    }
+}
+
+#[cfg(not(feature = "debug_verify"))]
+impl<T: Clone> Cache<T> {
     /// If cache is valid, then return previusly stored value. Otherwise compute new value with `f` and store it.
     pub(crate) fn get_or_else(&self, f: impl Fn() -> T) -> T {
-        *self.val.borrow_mut().get_or_insert_with(f)
+        if self.disabled.get() {
+            self.miss();
+            return f();
+        }
+        if let Some(v) = self.val.borrow().as_ref() {
+            self.hits.set(self.hits.get() + 1);
+            return v.clone();
+        }
+        self.miss();
+        let v = f();
+        self.val.replace(Some(v.clone()));
+        v
+    }
+}
+
+#[cfg(feature = "debug_verify")]
+impl<T: Clone + 'static> Cache<T> {
+    /// If cache is valid, then return previusly stored value and occasionally verify it.
+    /// Otherwise compute new value with `f` and store it.
+    pub(crate) fn get_or_else(&self, f: impl Fn() -> T) -> T {
+        if self.disabled.get() {
+            self.miss();
+            return f();
+        }
+        let stored = self.val.borrow().clone();
+        match stored {
+            Some(v) => {
+                self.hits.set(self.hits.get() + 1);
+                if self.verified && self.hits.get() % VERIFY_PERIOD == 0 {
+                    verify(&v, &f());
+                }
+                v
+            }
+            None => {
+                self.miss();
+                let v = f();
+                self.val.replace(Some(v.clone()));
+                v
+            }
+        }
+    }
+}
+
+impl<T: Clone> Cache<T> {
+    /// Count the computation of the value.
+    fn miss(&self) {
+        self.misses.set(self.misses.get() + 1);
+        self.computed_at.set(generation());
+    }
+
+    /// Returns the number of the values computed by the cache since its creation or the last reset.
+    pub(crate) fn misses(&self) -> u64 {
+        self.misses.get()
+    }
+
+    pub(crate) fn reset_misses(&self) {
+        self.misses.set(0);
+    }
+
+    /// Returns the number of the requests served with the stored value.
+    pub(crate) fn hits(&self) -> u64 {
+        self.hits.get()
+    }
+
+    /// Returns the input generation of the last computation of the value.
+    pub(crate) fn computed_at(&self) -> u64 {
+        self.computed_at.get()
+    }
+
+    /// Returns the priority of the refresh of the value, see [`Node::priority`](crate::node::Node::priority).
+    pub(crate) fn priority(&self) -> u8 {
+        self.priority.get()
+    }
+
+    pub(crate) fn set_priority(&self, p: u8) {
+        self.priority.set(p);
     }
 
+    /// Returns `true` if the cache stores a valid value.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.val.borrow().is_some()
+    }
+
+    #[cfg(test)]
     pub(crate) fn get(&self) -> Option<T> {
-        *self.val.borrow()
+        self.val.borrow().clone()
+    }
+
+    /// Returns `true` if the cache stores the computed values.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.disabled.get()
+    }
+
+    /// Enable or disable storing of the computed values.
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.disabled.set(!enabled);
+        self.invalidate();
     }
 
     /// Invalidate cache so that susequent request to it will lead to recomputations.
@@ -29,6 +174,69 @@ impl<T: Copy> Cache<T> {
     }
 }
 
+/// Implements the [`Node`](crate::node::Node) methods of the invalidation, the dependents and the cache statistics
+/// for the node type with the cache in the field `cached` and the dependent nodes in the field `dep`.
+macro_rules! cached_node_methods {
+    () => {
+        fn invalidate(&self) {
+            self.dep.invalidate_with(|| {
+                self.cached.invalidate();
+                $crate::node::notify_invalidate(self.id());
+            });
+        }
+
+        fn add_dependent(&self, n: std::rc::Rc<dyn $crate::node::Dependent>) {
+            self.dep.add(n);
+        }
+
+        fn take_dependents(&self) -> Vec<std::rc::Weak<dyn $crate::node::Dependent>> {
+            self.dep.take()
+        }
+
+        fn restore_dependents(&self, deps: Vec<std::rc::Weak<dyn $crate::node::Dependent>>) {
+            self.dep.restore(deps);
+        }
+
+        fn recompute_count(&self) -> u64 {
+            self.cached.misses()
+        }
+
+        fn reset_recompute_count(&self) {
+            self.cached.reset_misses();
+        }
+
+        fn cache_hits(&self) -> u64 {
+            self.cached.hits()
+        }
+
+        fn is_cached(&self) -> bool {
+            self.cached.is_valid()
+        }
+
+        fn computed_at(&self) -> Option<u64> {
+            Some(self.cached.computed_at())
+        }
+
+        fn set_caching(&self, enabled: bool) {
+            self.cached.set_enabled(enabled);
+        }
+
+        fn is_caching(&self) -> bool {
+            self.cached.is_enabled()
+        }
+
+        fn priority(&self) -> u8 {
+            self.cached.priority()
+        }
+
+        fn set_priority(&self, p: u8) {
+            self.cached.set_priority(p);
+        }
+    };
+}
+
+pub(crate) use cached_node_methods;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +267,69 @@ mod tests {
         cache.invalidate();
         assert_eq!(cache.get_or_else(|| -5.0), -5.0); 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_cache_misses() {
+        let cache = Cache::new();
+        cache.get_or_else(|| 1.0);
+        cache.get_or_else(|| 2.0);
+        assert_eq!(cache.misses(), 1);
+        cache.invalidate();
+        cache.get_or_else(|| 2.0);
+        assert_eq!(cache.misses(), 2);
+        cache.reset_misses();
+        assert_eq!(cache.misses(), 0);
+    }
+
+    #[test]
+    fn test_cache_disabled() {
+        let cache = Cache::new();
+        cache.set_enabled(false);
+        assert_eq!(cache.get_or_else(|| 1.0), 1.0);
+        assert_eq!(cache.get_or_else(|| 2.0), 2.0);
+        assert!(cache.get().is_none());
+        assert_eq!(cache.misses(), 2);
+    }
+
+    #[cfg(feature = "debug_verify")]
+    #[test]
+    fn test_cache_verify() {
+        let cache = Cache::new();
+        cache.get_or_else(|| 1.0f32);
+        for _ in 0..2 * VERIFY_PERIOD {
+            assert_eq!(cache.get_or_else(|| 1.0 + 1e-7), 1.0);
+        }
+    }
+
+    #[cfg(feature = "debug_verify")]
+    #[test]
+    #[should_panic(expected = "missing invalidation")]
+    fn test_cache_verify_mismatch() {
+        use crate::node::Node;
+        use crate::operations::Unary;
+        // node that reads the value outside of the graph and so is not invalidated on its change
+        let outside = std::rc::Rc::new(Cell::new(1.0f32));
+        let x = crate::create_input::<f32>("x");
+        let broken = {
+            let outside = outside.clone();
+            Unary::new(x.clone(), move |x| x + outside.get())
+        };
+        broken.compute();
+        outside.set(2.0);
+        for _ in 0..VERIFY_PERIOD {
+            broken.compute();
+        }
+    }
+
+    #[test]
+    fn test_cache_hits() {
+        let cache = Cache::new();
+        cache.get_or_else(|| 1.0);
+        assert!(cache.is_valid());
+        cache.get_or_else(|| 1.0);
+        cache.get_or_else(|| 1.0);
+        assert_eq!(cache.hits(), 2);
+        cache.invalidate();
+        assert!(!cache.is_valid());
+    }
+}
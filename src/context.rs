@@ -0,0 +1,385 @@
+//! Context that shares the input nodes between several fragments of the computational graph by name
+//! and collects the statistics of the session.
+
+use crate::analysis::nodes_of;
+use crate::node::Node;
+use crate::operations::Input;
+use crate::optimize::rebuild;
+use crate::utils::{constant, mul};
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+thread_local! {
+    static NAMES: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+}
+
+/// Returns the interned copy of the name, so the names read at runtime can label the inputs of the context.
+/// Each distinct name is allocated once per thread and leaked: it is never freed, even when the context
+/// or the thread ends, so the memory grows with the number of the distinct names without bound.
+fn intern(name: &str) -> &'static str {
+    NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        if let Some(&n) = names.get(name) {
+            return n;
+        }
+        let n: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        names.insert(n);
+        n
+    })
+}
+
+/// GraphContext keeps a single input node per name, so that all the graph fragments built
+/// through the same context are wired to the same `Rc<Input>`.
+#[derive(Default)]
+pub struct GraphContext {
+    inputs: RefCell<HashMap<&'static str, Rc<Input<'static>>>>,
+    stats: RefCell<Option<Rc<SessionStats>>>
+}
+
+/// Aggregate metrics of the session reported by [`SessionStats::report`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatsReport {
+    /// Total number of the recomputations of the node values.
+    pub recomputes: u64,
+    /// Total number of the requests of the node values served from the caches.
+    pub hits: u64,
+    /// Peak number of the nodes with the valid cached value after a computation.
+    pub peak_cached: usize,
+    /// Total number of the input changes.
+    pub sets: u64
+}
+
+/// SessionStats accumulates the metrics of all the computations and input changes made through
+/// the [`GraphContext`] it is registered on.
+#[derive(Default)]
+pub struct SessionStats {
+    report: Cell<StatsReport>
+}
+
+impl SessionStats {
+    pub fn new() -> Rc<Self> {
+        Default::default()
+    }
+
+    /// Returns the metrics accumulated so far.
+    pub fn report(&self) -> StatsReport {
+        self.report.get()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut StatsReport)) {
+        let mut report = self.report.get();
+        f(&mut report);
+        self.report.set(report);
+    }
+}
+
+impl GraphContext {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the input node with a given name, creating it on the first request.
+    pub fn input(&self, name: &str) -> Rc<Input<'static>> {
+        if let Some(x) = self.get(name) {
+            return x;
+        }
+        let name = intern(name);
+        let x = Rc::new(Input::new(name));
+        self.inputs.borrow_mut().insert(name, x.clone());
+        x
+    }
+
+    /// Returns the input node with a given name if it is already registered in the context.
+    pub fn get(&self, name: &str) -> Option<Rc<Input<'static>>> {
+        self.inputs.borrow().get(name).cloned()
+    }
+
+    /// Register the collector of the session statistics.
+    pub fn register_stats(&self, stats: Rc<SessionStats>) {
+        self.stats.replace(Some(stats));
+    }
+
+    /// Set new value of the input with a given name, creating the input on the first request.
+    pub fn set(&self, name: &str, x: f32) {
+        self.input(name).set(x);
+        if let Some(stats) = self.stats.borrow().as_ref() {
+            stats.update(|r| r.sets += 1);
+        }
+    }
+
+    /// Returns the value of `root`, accounting the recomputations and cache hits of its nodes in the statistics.
+    pub fn compute(&self, root: &Rc<dyn Node<Output = f32>>) -> f32 {
+        let stats = match self.stats.borrow().clone() {
+            Some(stats) => stats,
+            None => return root.compute()
+        };
+        let nodes = nodes_of(root);
+        let count = |f: fn(&Rc<dyn Node<Output = f32>>) -> u64| nodes.iter().map(f).sum::<u64>();
+        let (recomputes, hits) = (count(|n| n.recompute_count()), count(|n| n.cache_hits()));
+        let res = root.compute();
+        let cached = nodes.iter().filter(|n| n.is_cached()).count();
+        stats.update(|r| {
+            r.recomputes += count(|n| n.recompute_count()) - recomputes;
+            r.hits += count(|n| n.cache_hits()) - hits;
+            r.peak_cached = r.peak_cached.max(cached);
+        });
+        res
+    }
+
+    /// Merge inputs of `other` into this context. The inputs with names unknown to this context are adopted as is.
+    /// The inputs with the same name are made to follow the input of this context, so the fragments
+    /// built with `other` respond to the changes of this context's input.
+    pub fn merge(&self, other: GraphContext) {
+        for (name, input) in other.inputs.into_inner() {
+            let mut inputs = self.inputs.borrow_mut();
+            match inputs.get(name) {
+                Some(own) => input.follow(own.clone()),
+                None => { inputs.insert(name, input); }
+            }
+        }
+    }
+}
+
+impl GraphContext {
+    /// Parses the graph fragment from the expression in the format of [`expression`](crate::display::expression),
+    /// e.g. `x1 + (x2 * sin(x2 + (x3 ^ 3)))`, and returns its root. The names are resolved to the inputs
+    /// of the context, so the fragments parsed by the same context share the inputs. The numbers become
+    /// constants, and the functions are the built-in operations, e.g. `sin` or `pow`.
+    pub fn parse(&self, expr: &str) -> Result<Rc<dyn Node<Output = f32>>, ParseError> {
+        let mut p = Parser { ctx: self, src: expr, pos: 0 };
+        let root = p.sum()?;
+        p.skip_spaces();
+        match p.peek() {
+            None => Ok(root),
+            Some(c) => Err(p.error(format!("unexpected '{}'", c)))
+        }
+    }
+}
+
+/// Error of [`GraphContext::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte position of the error in the expression.
+    pub position: usize,
+    /// Description of the error.
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Recursive descent parser of the expressions with the usual precedence: `+ -`, then `* /`, then `^`,
+/// that is right-associative.
+struct Parser<'c, 's> {
+    ctx: &'c GraphContext,
+    src: &'s str,
+    pos: usize
+}
+
+type Parsed = Result<Rc<dyn Node<Output = f32>>, ParseError>;
+
+impl Parser<'_, '_> {
+    fn error(&self, message: String) -> ParseError {
+        ParseError { position: self.pos, message }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Consumes the character `c` if it is the next one after the spaces.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_spaces();
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    /// Consumes the longest prefix of the characters satisfying `f`.
+    fn take_while(&mut self, mut f: impl FnMut(char) -> bool) -> &str {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|&c| f(c)) {
+            self.pos += c.len_utf8();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn op(&self, label: &str, operands: &[Rc<dyn Node<Output = f32>>]) -> Parsed {
+        rebuild(label, operands).ok_or_else(|| self.error(format!("unknown operation {}/{}", label, operands.len())))
+    }
+
+    fn sum(&mut self) -> Parsed {
+        let mut x = self.product()?;
+        loop {
+            let label = if self.eat('+') { "add" } else if self.eat('-') { "sub" } else { return Ok(x) };
+            let y = self.product()?;
+            x = self.op(label, &[x, y])?;
+        }
+    }
+
+    fn product(&mut self) -> Parsed {
+        let mut x = self.power()?;
+        loop {
+            let label = if self.eat('*') { "mul" } else if self.eat('/') { "div" } else { return Ok(x) };
+            let y = self.power()?;
+            x = self.op(label, &[x, y])?;
+        }
+    }
+
+    fn power(&mut self) -> Parsed {
+        let x = self.atom()?;
+        if self.eat('^') {
+            let e = self.power()?;
+            return self.op("pow", &[x, e]);
+        }
+        Ok(x)
+    }
+
+    fn atom(&mut self) -> Parsed {
+        self.skip_spaces();
+        if self.eat('(') {
+            let x = self.sum()?;
+            return if self.eat(')') { Ok(x) } else { Err(self.error(String::from("expected ')'"))) };
+        }
+        if self.eat('-') {
+            let x = self.power()?;
+            return Ok(match x.constant_value() {
+                Some(v) => constant(-v),
+                None => mul(constant(-1.0), x)
+            });
+        }
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                let mut prev = ' ';
+                // The sign belongs to the number only after the exponent mark, e.g. `1e-3`.
+                let number = self.take_while(|c| {
+                    let ok = c.is_ascii_alphanumeric() || c == '.' || ((c == '-' || c == '+') && prev == 'e');
+                    prev = c;
+                    ok
+                });
+                let message = format!("bad number {}", number);
+                number.parse().map(constant).map_err(|_| ParseError { position: start, message })
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_').to_owned();
+                if !self.eat('(') {
+                    return Ok(self.ctx.input(&name));
+                }
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.sum()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(self.error(String::from("expected ',' or ')'")));
+                        }
+                    }
+                }
+                self.op(&name, &args)
+            }
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+            None => Err(self.error(String::from("unexpected end")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{add, mul};
+
+    #[test]
+    fn test_context_reuse_input() {
+        let ctx = GraphContext::new();
+        let x1 = ctx.input("x1");
+        assert!(Rc::ptr_eq(&x1, &ctx.input("x1")));
+        assert!(ctx.get("x2").is_none());
+    }
+
+    #[test]
+    fn test_context_merge() {
+        let ctx = GraphContext::new();
+        let fragment1 = add(ctx.input("x1"), ctx.input("x2"));
+        let other = GraphContext::new();
+        let fragment2 = mul(other.input("x1"), other.input("x3"));
+        ctx.merge(other);
+        ctx.input("x2").set(1.0);
+        ctx.input("x3").set(2.0);
+        ctx.input("x1").set(3.0);
+        assert_eq!(fragment1.compute(), 4.0);
+        assert_eq!(fragment2.compute(), 6.0);
+        ctx.input("x1").set(5.0);
+        assert_eq!(fragment1.compute(), 6.0);
+        assert_eq!(fragment2.compute(), 10.0);
+    }
+
+    #[test]
+    fn test_session_stats() {
+        let ctx = GraphContext::new();
+        let stats = SessionStats::new();
+        ctx.register_stats(stats.clone());
+        let shared = add(ctx.input("x1"), ctx.input("x2"));
+        let graph = mul(shared.clone(), shared.clone());
+        ctx.set("x1", 1.0);
+        ctx.set("x2", 2.0);
+        assert_eq!(ctx.compute(&graph), 9.0);
+        // both nodes are recomputed, the second request of the shared node is a hit
+        assert_eq!(stats.report(), StatsReport { recomputes: 2, hits: 1, peak_cached: 2, sets: 2 });
+        assert_eq!(ctx.compute(&graph), 9.0);
+        ctx.set("x2", 3.0);
+        assert_eq!(ctx.compute(&graph), 16.0);
+        assert_eq!(stats.report(), StatsReport { recomputes: 4, hits: 3, peak_cached: 2, sets: 3 });
+    }
+
+    #[test]
+    fn test_parse_fragments_share_inputs() {
+        use crate::display::expression;
+        let ctx = GraphContext::new();
+        // names read at runtime, as from a file
+        let sources: Vec<String> = vec![String::from("x1 + (x2 * sin(x2 + (x3 ^ 3)))"), format!("{} * -2", "x1")];
+        let fragments: Vec<_> = sources.iter().map(|s| ctx.parse(s).unwrap()).collect();
+        assert_eq!(expression(&fragments[0]), sources[0]);
+        ctx.set("x2", 2.0);
+        ctx.set("x3", 3.0);
+        ctx.input(&String::from("x1")).set(1.0);
+        assert_eq!(fragments[0].compute(), 1.0 + 2.0 * 29f32.sin());
+        assert_eq!(fragments[1].compute(), -2.0);
+        ctx.set("x1", 4.0);
+        assert_eq!(fragments[0].compute(), 4.0 + 2.0 * 29f32.sin());
+        assert_eq!(fragments[1].compute(), -8.0);
+        assert_eq!(ctx.parse("x1 + cos(x2)").err().map(|e| e.position), Some(12));
+        assert!(ctx.parse("(x1").is_err());
+    }
+
+    #[test]
+    fn test_parse_unicode() {
+        let ctx = GraphContext::new();
+        let graph = ctx.parse("x1\u{00A0}+\u{3000}x2").unwrap();
+        ctx.set("x1", 1.0);
+        ctx.set("x2", 2.0);
+        assert_eq!(graph.compute(), 3.0);
+        let err = ctx.parse("x1 + \u{00A0}€").err().unwrap();
+        assert_eq!(err.position, 7);
+        assert_eq!(err.to_string(), "unexpected '€' at 7");
+    }
+}
@@ -0,0 +1,290 @@
+//! Human-readable formatting of the computational graph as expressions and DOT graphs.
+
+use crate::analysis::{nodes_of, topological_order};
+use crate::node::Node;
+use crate::optimize::simplify;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+/// Returns the infix symbol of the built-in binary operation with a given label.
+fn infix(label: &str) -> Option<&'static str> {
+    match label {
+        "add" => Some("+"),
+        "sub" => Some("-"),
+        "mul" => Some("*"),
+        "div" => Some("/"),
+        "pow" => Some("^"),
+        _ => None
+    }
+}
+
+/// Format the operation with a given label on the formatted operands,
+/// e.g. `a + b` for the built-in binary operations and `sin(a)` for the others.
+fn format_op(label: &str, operands: &[String]) -> String {
+    match (infix(label), operands) {
+        (Some(op), [x, y]) => format!("{} {} {}", x, op, y),
+        (_, []) => label.to_string(),
+        _ => format!("{}({})", label, operands.join(", "))
+    }
+}
+
+/// Formatting of the subexpressions replacing the whole subexpression, e.g. by its folded value.
+type Folding<'a, T> = &'a dyn Fn(&Rc<dyn Node<Output = T>>) -> Option<String>;
+
+/// Format the operand of an infix operation, wrapping the infix operations in parentheses.
+fn operand<T>(n: &Rc<dyn Node<Output = T>>, fold: Folding<T>) -> String {
+    if let Some(e) = fold(n) {
+        return e;
+    }
+    let e = expression_by(n, fold);
+    if infix(&n.label()).is_some() && n.children().len() == 2 {
+        format!("({})", e)
+    } else {
+        e
+    }
+}
+
+/// Format the graph as an expression with the subexpressions replaced by `fold`.
+fn expression_by<T>(root: &Rc<dyn Node<Output = T>>, fold: Folding<T>) -> String {
+    if let Some(e) = fold(root) {
+        return e;
+    }
+    let children = root.children();
+    let operands: Vec<String> = match infix(&root.label()) {
+        Some(_) => children.iter().map(|c| operand(c, fold)).collect(),
+        None => children.iter().map(|c| expression_by(c, fold)).collect()
+    };
+    format_op(&root.label(), &operands)
+}
+
+/// Returns the graph as an expression, e.g. `x1 + (x2 * sin(x2 + (x3 ^ 3)))`.
+/// Operations are formatted with their labels, the operands of infix operations are parenthesized.
+pub fn expression<T>(root: &Rc<dyn Node<Output = T>>) -> String {
+    expression_by(root, &|_| None)
+}
+
+/// Returns the graph simplified by [`simplify`] as an expression like [`expression`], with the operations
+/// on the constants only folded to their values, e.g. `x1 * 5` for `(x1 + 0) * (2 + 3)`.
+pub fn symbolic(root: &Rc<dyn Node<Output = f32>>) -> String {
+    expression_by(&simplify(root), &|n| (n.is_constant() && !n.children().is_empty()).then(|| n.compute().to_string()))
+}
+
+/// Returns step-by-step explanation of the computation of `root`: for each operation in the topological order
+/// it gives the operation on the inputs, the operation on the input values and the result,
+/// e.g. `x3 ^ 3 = 3 ^ 3 = 27; x2 + (x3 ^ 3) = 2 + 27 = 29; sin(x2 + (x3 ^ 3)) = sin(29) = -0.6636339`.
+pub fn explain<T: Display>(root: &Rc<dyn Node<Output = T>>) -> String {
+    topological_order(root).iter()
+        .filter(|n| !n.children().is_empty())
+        .map(|n| {
+            let values: Vec<String> = n.children().iter().map(|c| c.compute().to_string()).collect();
+            format!("{} = {} = {}", expression(n), format_op(&n.label(), &values), n.compute())
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Returns the graph in the DOT format of Graphviz with one vertex per node labelled with the node label,
+/// and the edges from each operation to its inputs. The shared nodes appear once with several incoming edges.
+pub fn to_dot<T>(root: &Rc<dyn Node<Output = T>>) -> String {
+    dot_with_labels(root, |n| n.label())
+}
+
+/// Returns the graph in the DOT format like [`to_dot`], with the labels annotated with the values of the nodes,
+/// e.g. `add = 29`. The values that are not cached yet are computed.
+pub fn to_dot_with_values<T: Display>(root: &Rc<dyn Node<Output = T>>) -> String {
+    dot_with_labels(root, |n| format!("{} = {}", n.label(), n.compute()))
+}
+
+/// Format the graph in the DOT format with the labels of the vertices given by `label`.
+fn dot_with_labels<T>(root: &Rc<dyn Node<Output = T>>, label: impl Fn(&Rc<dyn Node<Output = T>>) -> String) -> String {
+    let mut res = String::from("digraph {\n");
+    for n in nodes_of(root) {
+        res += &format!("    n{} [label={:?}];\n", n.id(), label(&n));
+        for c in n.children() {
+            res += &format!("    n{} -> n{};\n", n.id(), c.id());
+        }
+    }
+    res + "}\n"
+}
+
+/// Error of [`to_wgsl`] on the graph that can not be expressed in WGSL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WgslError {
+    /// The operation of the node with a given id has no WGSL counterpart.
+    UnsupportedOp { node: usize, label: String },
+    /// The constant of the node with a given id is NaN or infinite, that WGSL literals can not express.
+    NonFiniteConstant { node: usize, value: f32 }
+}
+
+impl fmt::Display for WgslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WgslError::UnsupportedOp { node, label } => write!(f, "operation {} of node {} is not supported in WGSL", label, node),
+            WgslError::NonFiniteConstant { node, value } => write!(f, "constant {} of node {} is not finite", value, node)
+        }
+    }
+}
+
+impl Error for WgslError {}
+
+/// Returns the WGSL built-in function computing the operation with a given label on `arity` operands.
+fn wgsl_function(label: &str, arity: usize) -> Option<&'static str> {
+    match (label, arity) {
+        ("pow", 2) => Some("pow"),
+        ("sin", 1) => Some("sin"),
+        ("tanh", 1) => Some("tanh"),
+        _ => None
+    }
+}
+
+/// Returns WGSL shader function `fn_name` computing the value of `root` from the struct `Inputs` of the input values,
+/// e.g. the uniform buffer of the shader. The struct is expected to have the `f32` field per input named by its label,
+/// or by the label and the id of the node, e.g. `x_1234`, for the labels shared by several inputs.
+/// Each input is bound with `let`, as well as the operations used by several nodes, the other operations are inlined.
+/// Only the arithmetic operations and the ones with the WGSL built-in counterparts (`pow`, `sin`, `tanh`)
+/// are supported, the other operations and the non-finite constants are reported as the error.
+pub fn to_wgsl(root: &Rc<dyn Node<Output = f32>>, fn_name: &str) -> Result<String, WgslError> {
+    let order = topological_order(root);
+    let mut uses: HashMap<usize, usize> = HashMap::new();
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    for n in &order {
+        for c in n.children() {
+            *uses.entry(c.id()).or_default() += 1;
+        }
+        if n.is_input() {
+            *labels.entry(n.label()).or_default() += 1;
+        }
+    }
+    // expression of each node and whether it is an infix operation to be parenthesized as an operand
+    let mut exprs: HashMap<usize, (String, bool)> = HashMap::new();
+    let mut res = format!("fn {}(inputs: Inputs) -> f32 {{\n", fn_name);
+    for n in &order {
+        let label = n.label();
+        let e = if n.is_input() {
+            let name = if labels[&label] > 1 { format!("{}_{}", label, n.id()) } else { label };
+            res += &format!("    let {} = inputs.{};\n", name, name);
+            (name, false)
+        } else if let Some(v) = n.constant_value() {
+            if !v.is_finite() {
+                return Err(WgslError::NonFiniteConstant { node: n.id(), value: v });
+            }
+            (format!("{:?}", v), false)
+        } else {
+            let operands: Vec<&(String, bool)> = n.children().iter().map(|c| &exprs[&c.id()]).collect();
+            match (infix(&label), wgsl_function(&label, operands.len()), operands.as_slice()) {
+                (Some(op), None, [x, y]) => {
+                    let wrap = |(e, infix): &(String, bool)| if *infix { format!("({})", e) } else { e.clone() };
+                    (format!("{} {} {}", wrap(x), op, wrap(y)), true)
+                }
+                (_, Some(f), _) => {
+                    let operands: Vec<&str> = operands.iter().map(|(e, _)| e.as_str()).collect();
+                    (format!("{}({})", f, operands.join(", ")), false)
+                }
+                _ => return Err(WgslError::UnsupportedOp { node: n.id(), label })
+            }
+        };
+        let e = if uses.get(&n.id()).copied().unwrap_or(0) > 1 && !n.is_input() && n.constant_value().is_none() {
+            res += &format!("    let t{} = {};\n", n.id(), e.0);
+            (format!("t{}", n.id()), false)
+        } else {
+            e
+        };
+        exprs.insert(n.id(), e);
+    }
+    Ok(res + &format!("    return {};\n}}\n", exprs[&root.id()].0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::readme_graph;
+    use crate::*;
+
+    #[test]
+    fn test_expression() {
+        let (graph, _) = readme_graph();
+        assert_eq!(expression(&graph), "x1 + (x2 * sin(x2 + (x3 ^ 3)))");
+    }
+
+    #[test]
+    fn test_explain() {
+        let x2 = create_input("x2");
+        let x3 = create_input("x3");
+        let graph = sin(add(x2.clone(), pow_f32(x3.clone(), 3f32)));
+        x2.set(2.0);
+        x3.set(3.0);
+        let explanation = explain(&graph);
+        assert!(explanation.starts_with("x3 ^ 3 = 3 ^ 3 = 27; x2 + (x3 ^ 3) = 2 + 27 = 29; "));
+        assert!(explanation.contains(&format!("sin(x2 + (x3 ^ 3)) = sin(29) = {}", 29f32.sin())));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let shared = add(x1.clone(), x2.clone());
+        let graph = mul(shared.clone(), sin(shared.clone()));
+        let dot = to_dot(&graph);
+        assert!(dot.starts_with("digraph {\n") && dot.ends_with("}\n"));
+        assert_eq!(dot.matches("[label=").count(), 5);
+        assert!(dot.contains(&format!("n{} [label=\"x1\"];", x1.id())));
+        assert_eq!(dot.matches(&format!("-> n{};", shared.id())).count(), 2);
+    }
+
+    #[test]
+    fn test_to_dot_with_values() {
+        let (graph, _) = readme_graph();
+        let dot = to_dot_with_values(&graph);
+        for label in ["x1 = 1", "x2 = 2", "x3 = 3", "pow = 27", "add = 29", &format!("sin = {}", 29f32.sin())] {
+            assert!(dot.contains(&format!("[label=\"{}\"]", label)), "{}", label);
+        }
+        assert_eq!(dot.matches("[label=").count(), 9);
+    }
+
+    #[test]
+    fn test_to_wgsl() {
+        let (graph, [x1, _, _]) = readme_graph();
+        let wgsl = to_wgsl(&graph, "graph").unwrap();
+        assert!(wgsl.starts_with("fn graph(inputs: Inputs) -> f32 {\n"));
+        for x in ["x1", "x2", "x3"] {
+            assert!(wgsl.contains(&format!("    let {} = inputs.{};\n", x, x)));
+        }
+        assert!(wgsl.contains("    return x1 + (x2 * sin(x2 + pow(x3, 3.0)));\n}\n"));
+
+        let s = sin(x1.clone());
+        let shared = mul(s.clone(), s.clone());
+        let wgsl = to_wgsl(&shared, "shared").unwrap();
+        assert!(wgsl.contains(&format!("    let t{} = sin(x1);\n", s.id())));
+        assert!(wgsl.contains(&format!("    return t{} * t{};\n", s.id(), s.id())));
+    }
+
+    #[test]
+    fn test_to_wgsl_errors() {
+        let x = create_input("x");
+        let other = create_input("x");
+        let wgsl = to_wgsl(&sub(x.clone(), other.clone()), "f").unwrap();
+        assert!(wgsl.contains(&format!("    let x_{} = inputs.x_{};\n", x.id(), x.id())));
+        assert!(wgsl.contains(&format!("    return x_{} - x_{};\n", x.id(), other.id())));
+
+        let graph = safe_div(x.clone(), other.clone(), 0.0);
+        let err = to_wgsl(&graph, "f").unwrap_err();
+        assert_eq!(err, WgslError::UnsupportedOp { node: graph.id(), label: String::from("safe_div") });
+        let inf = constant(f32::INFINITY);
+        let err = to_wgsl(&add(x.clone(), inf.clone()), "f").unwrap_err();
+        assert_eq!(err, WgslError::NonFiniteConstant { node: inf.id(), value: f32::INFINITY });
+        let custom: Rc<dyn Node<Output = f32>> = crate::operations::Binary::named("sin", x.clone(), other.clone(), |x, y| x + y);
+        assert!(to_wgsl(&custom, "f").is_err());
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let x1 = create_input("x1");
+        assert_eq!(symbolic(&add(mul(x1.clone(), constant(1.0)), constant(0.0))), "x1");
+        assert_eq!(symbolic(&add(constant(2.0), constant(3.0))), "5");
+        let graph = mul(add(x1.clone(), constant(0.0)), sin(add(constant(2.0), constant(3.0))));
+        assert_eq!(symbolic(&graph), format!("x1 * {}", 5f32.sin()));
+    }
+}
@@ -0,0 +1,152 @@
+//! Dynamically typed nodes, that compose the nodes of different value types at runtime.
+//! The typed nodes are bridged to [`DynNode`] returning [`Value`], and [`dyn_op`] combines such nodes
+//! with caching of the resulting value.
+
+use crate::node::{Node, Dependent, Dependencies, notify_invalidate};
+use crate::cache::Cache;
+
+use std::rc::Rc;
+
+/// Value of the dynamically typed node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Scalar(f32),
+    Vector(Vec<f32>),
+    Bool(bool)
+}
+
+/// Node returning a dynamically typed value.
+pub trait DynNode {
+    /// Provides the value of the node.
+    fn compute(&self) -> Value;
+    /// Invalidate the cache of the current node and the dependent nodes.
+    fn invalidate(&self);
+    /// Add some node n to the list of the nodes that are dependent of the value of this node.
+    fn add_dependent(&self, n: Rc<dyn Dependent>);
+    /// Returns the label of the node.
+    fn label(&self) -> String {
+        String::from("node")
+    }
+    /// Returns the identity of the node, that is the address of the node in the memory.
+    fn id(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+/// Typed node converting its value to `Value` with `to_value`.
+struct Bridge<T> {
+    node: Rc<dyn Node<Output = T>>,
+    to_value: fn(T) -> Value
+}
+
+impl<T> DynNode for Bridge<T> {
+    fn compute(&self) -> Value {
+        (self.to_value)(self.node.compute())
+    }
+
+    fn invalidate(&self) {
+        self.node.invalidate();
+    }
+
+    fn add_dependent(&self, n: Rc<dyn Dependent>) {
+        self.node.add_dependent(n);
+    }
+
+    fn label(&self) -> String {
+        self.node.label()
+    }
+}
+
+/// Bridges the scalar node to the dynamically typed node.
+pub fn from_scalar(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn DynNode> {
+    Rc::new(Bridge { node: x, to_value: Value::Scalar })
+}
+
+/// Bridges the vector node of `N` elements to the dynamically typed node.
+pub fn from_vector<const N: usize>(x: Rc<dyn Node<Output = [f32; N]>>) -> Rc<dyn DynNode> {
+    Rc::new(Bridge { node: x, to_value: |v| Value::Vector(v.to_vec()) })
+}
+
+/// Bridges the boolean node to the dynamically typed node.
+pub fn from_bool(x: Rc<dyn Node<Output = bool>>) -> Rc<dyn DynNode> {
+    Rc::new(Bridge { node: x, to_value: Value::Bool })
+}
+
+/// DynOp node applies operation `op` to the values of the dynamically typed nodes `xs` and caches the result.
+pub struct DynOp<F: Fn(&[Value]) -> Value> {
+    label: &'static str,
+    xs: Vec<Rc<dyn DynNode>>,
+    op: F,
+    cached: Cache<Value>,
+    dep: Dependencies
+}
+
+impl<F: Fn(&[Value]) -> Value> DynNode for DynOp<F> {
+    fn compute(&self) -> Value {
+        self.cached.get_or_else(|| {
+            let values: Vec<Value> = self.xs.iter().map(|x| x.compute()).collect();
+            (self.op)(&values)
+        })
+    }
+
+    fn invalidate(&self) {
+        self.dep.invalidate_with(|| {
+            self.cached.invalidate();
+            notify_invalidate(self.id());
+        });
+    }
+
+    fn add_dependent(&self, n: Rc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+}
+
+impl<F: Fn(&[Value]) -> Value> Dependent for DynOp<F> {
+    fn invalidate_dependent(&self) {
+        DynNode::invalidate(self);
+    }
+}
+
+/// Creates dynamically typed node with a given label, that applies `op` to the values of `xs` and caches the result.
+pub fn dyn_op<F: Fn(&[Value]) -> Value + 'static>(label: &'static str, xs: Vec<Rc<dyn DynNode>>, op: F) -> Rc<dyn DynNode> {
+    let tmp = Rc::new(DynOp { label, xs: xs.clone(), op, cached: Cache::new(), dep: Default::default() });
+    for x in xs {
+        x.add_dependent(tmp.clone());
+    }
+    tmp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_input;
+    use crate::typed::vector_input;
+
+    #[test]
+    fn test_scalar_vector() {
+        let a = create_input("a");
+        let v = vector_input::<3>("v");
+        let scaled = dyn_op("scale", vec![from_scalar(a.clone()), from_vector(v.clone())], |xs| match xs {
+            [Value::Scalar(a), Value::Vector(v)] => Value::Vector(v.iter().map(|x| a * x).collect()),
+            _ => Value::Bool(false)
+        });
+        let positive = dyn_op("positive", vec![scaled.clone()], |xs| match &xs[0] {
+            Value::Vector(v) => Value::Bool(v.iter().all(|x| *x > 0.0)),
+            _ => Value::Bool(false)
+        });
+        a.set(2.0);
+        v.set([1.0, 2.0, 3.0]);
+        assert_eq!(scaled.compute(), Value::Vector(vec![2.0, 4.0, 6.0]));
+        assert_eq!(positive.compute(), Value::Bool(true));
+        a.set(-1.0);
+        match scaled.compute() {
+            Value::Vector(v) => assert_eq!(v, vec![-1.0, -2.0, -3.0]),
+            other => panic!("unexpected value {:?}", other)
+        }
+        assert_eq!(positive.compute(), Value::Bool(false));
+    }
+}
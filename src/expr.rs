@@ -0,0 +1,144 @@
+//! Expression handles over the graph nodes with arithmetic operators, to write the formula of the graph as is:
+//! ```
+//! # use computational_graph::create_input;
+//! # use computational_graph::expr::*;
+//! let x1 = create_input("x1");
+//! let x2 = create_input("x2");
+//! let (e1, e2) = (Expr::from(x1.clone()), Expr::from(x2.clone()));
+//! let graph = &e1 + &e2 * sin(&e2);
+//! x1.set(1.0);
+//! x2.set(0.0);
+//! assert_eq!(graph.compute(), 1.0);
+//! ```
+//! The operators create the same cached nodes as the functions of [`crate::utils`], and the cloned handle
+//! refers to the same node, so the reused subexpression is computed once.
+
+use crate::node::Node;
+use crate::utils;
+
+use std::ops::{Add, Deref, Div, Mul, Sub};
+use std::rc::Rc;
+
+/// Handle to a node producing a `f32` value, that supports arithmetic operators.
+#[derive(Clone)]
+pub struct Expr(Rc<dyn Node<Output = f32>>);
+
+impl Expr {
+    /// Returns the underlying node.
+    pub fn node(&self) -> Rc<dyn Node<Output = f32>> {
+        self.0.clone()
+    }
+}
+
+impl Deref for Expr {
+    type Target = Rc<dyn Node<Output = f32>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Node<Output = f32> + 'static> From<Rc<T>> for Expr {
+    fn from(x: Rc<T>) -> Self {
+        Expr(x)
+    }
+}
+
+impl From<Rc<dyn Node<Output = f32>>> for Expr {
+    fn from(x: Rc<dyn Node<Output = f32>>) -> Self {
+        Expr(x)
+    }
+}
+
+impl From<&Expr> for Expr {
+    fn from(x: &Expr) -> Self {
+        x.clone()
+    }
+}
+
+impl From<f32> for Expr {
+    fn from(x: f32) -> Self {
+        Expr(utils::constant(x))
+    }
+}
+
+impl From<Expr> for Rc<dyn Node<Output = f32>> {
+    fn from(x: Expr) -> Self {
+        x.0
+    }
+}
+
+/// Implements operator `$tr` for owned and borrowed expressions and for `f32` on the right side.
+macro_rules! impl_op {
+    ($tr:ident, $method:ident, $f:path) => {
+        impl<R: Into<Expr>> $tr<R> for Expr {
+            type Output = Expr;
+
+            fn $method(self, rhs: R) -> Expr {
+                Expr($f(self.0, rhs.into().0))
+            }
+        }
+
+        impl<R: Into<Expr>> $tr<R> for &Expr {
+            type Output = Expr;
+
+            fn $method(self, rhs: R) -> Expr {
+                Expr($f(self.node(), rhs.into().0))
+            }
+        }
+    };
+}
+
+impl_op!(Add, add, utils::add);
+impl_op!(Sub, sub, utils::sub);
+impl_op!(Mul, mul, utils::mul);
+impl_op!(Div, div, utils::div);
+
+/// Creates trigonometric sinus node of an expression.
+pub fn sin(x: impl Into<Expr>) -> Expr {
+    Expr(utils::sin(x.into().0))
+}
+
+/// Creates node that raise an expression to a given power.
+pub fn pow(x: impl Into<Expr>, e: f32) -> Expr {
+    Expr(utils::pow_f32(x.into().0, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_input;
+
+    // round to decimal digits
+    fn round(x: f32, precision: u32) -> f32 {
+        let m = 10i32.pow(precision) as f32;
+        (x * m).round() / m
+    }
+
+    #[test]
+    fn test_operators_reference() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let x3 = create_input("x3");
+        let (e1, e2, e3) = (Expr::from(x1.clone()), Expr::from(x2.clone()), Expr::from(x3.clone()));
+        let graph = &e1 + &e2 * sin(&e2 + pow(&e3, 3.0));
+        x1.set(1f32);
+        x2.set(2f32);
+        x3.set(3f32);
+        assert_eq!(round(graph.compute(), 5), -0.32727);
+        x1.set(2f32);
+        x2.set(3f32);
+        x3.set(4f32);
+        assert_eq!(round(graph.compute(), 5), -0.56656);
+    }
+
+    #[test]
+    fn test_shared_subexpression() {
+        let x = create_input("x");
+        let s = sin(x.clone());
+        let graph = (&s - 1.0) / (&s + 1.0);
+        x.set(0.0);
+        assert_eq!(graph.compute(), -1.0);
+        assert_eq!(s.recompute_count(), 1);
+    }
+}
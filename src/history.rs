@@ -0,0 +1,109 @@
+//! Versioning of the structure of the computational graph, e.g. for the undo in a graph editor.
+//!
+//! The committed version stores the structure as the list of the nodes in the topological order, where each
+//! built-in operation refers to its inputs by their positions in the list. The checkout rebuilds the operations,
+//! while the inputs, the constants and the other operations are kept as nodes and reused.
+
+use crate::analysis::topological_order;
+use crate::node::Node;
+use crate::optimize::{is_rebuildable, rebuild};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Version of the graph structure returned by [`History::commit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version(usize);
+
+/// Entry of the stored structure.
+enum Entry {
+    /// Node reused as it is.
+    Node(Rc<dyn Node<Output = f32>>),
+    /// Built-in operation with a given label on the entries with the given positions.
+    Op(String, Vec<usize>)
+}
+
+/// History of the committed versions of the graph structure.
+#[derive(Default)]
+pub struct History {
+    versions: RefCell<Vec<Vec<Entry>>>
+}
+
+impl History {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records the current structure of the graph of `root` and returns its version.
+    pub fn commit(&self, root: &Rc<dyn Node<Output = f32>>) -> Version {
+        let order = topological_order(root);
+        let pos: HashMap<usize, usize> = order.iter().enumerate().map(|(i, n)| (n.id(), i)).collect();
+        let entries = order.iter()
+            .map(|n| {
+                let children = n.children();
+                // Only the operations that can be rebuilt from their label are stored by the structure.
+                if !is_rebuildable(&n.label(), children.len()) {
+                    Entry::Node(n.clone())
+                } else {
+                    Entry::Op(n.label(), children.iter().map(|c| pos[&c.id()]).collect())
+                }
+            })
+            .collect();
+        let mut versions = self.versions.borrow_mut();
+        versions.push(entries);
+        Version(versions.len() - 1)
+    }
+
+    /// Rebuilds the graph with the structure of a given version and returns its root.
+    /// The inputs are shared with the committed graph, so the rebuilt graph follows their values.
+    pub fn checkout(&self, version: Version) -> Rc<dyn Node<Output = f32>> {
+        let versions = self.versions.borrow();
+        let mut nodes: Vec<Rc<dyn Node<Output = f32>>> = Vec::new();
+        for e in &versions[version.0] {
+            let n = match e {
+                Entry::Node(n) => n.clone(),
+                Entry::Op(label, children) => {
+                    let children: Vec<_> = children.iter().map(|&i| nodes[i].clone()).collect();
+                    rebuild(label, &children).expect("committed operation must be rebuilt")
+                }
+            };
+            nodes.push(n);
+        }
+        nodes.pop().expect("committed graph must have a root")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::display::expression;
+
+    #[test]
+    fn test_commit_checkout() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let history = History::new();
+        let shared = sin(x1.clone());
+        let graph = add(mul(shared.clone(), x2.clone()), shared.clone());
+        x1.set(1.0);
+        x2.set(2.0);
+        let value = graph.compute();
+        let v1 = history.commit(&graph);
+
+        let graph = sub(graph.clone(), x1.clone());
+        let v2 = history.commit(&graph);
+        assert_ne!(v1, v2);
+        assert_eq!(expression(&graph), "((sin(x1) * x2) + sin(x1)) - x1");
+
+        let restored = history.checkout(v1);
+        assert_eq!(expression(&restored), "(sin(x1) * x2) + sin(x1)");
+        assert_eq!(restored.compute(), value);
+        // the shared node stays shared
+        let children = restored.children();
+        assert!(Rc::ptr_eq(&children[0].children()[0], &children[1]));
+        x2.set(3.0);
+        assert_eq!(restored.compute(), 4.0 * 1f32.sin());
+    }
+}
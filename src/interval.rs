@@ -0,0 +1,211 @@
+//! Interval arithmetic over the computational graph: every node computes a range `(lo, hi)`
+//! that is guaranteed to contain the result for any input values from the input ranges.
+
+use crate::node::{Node, Dependent, Dependencies, next_generation};
+use crate::cache::{Cache, cached_node_methods};
+
+use std::cell::Cell;
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::rc::{Rc, Weak};
+
+/// Closed interval `(lo, hi)`.
+pub type Interval = (f32, f32);
+
+/// Input node present some interval input value.
+pub struct IntervalInput<'a> {
+    name: &'a str,
+    value: Cell<Interval>,
+    dep: Dependencies
+}
+
+impl<'a> IntervalInput<'a> {
+    pub fn new(name: &'a str) -> Self {
+        IntervalInput { name, value: Cell::new((0.0, 0.0)), dep: Default::default() }
+    }
+
+    /// Set new interval `[lo, hi]` and require invalidation of the caches of the dependent nodes.
+    /// Like [`Input::set`](crate::operations::Input::set), setting the interval equal to the current one is skipped.
+    pub fn set(&self, lo: f32, hi: f32) {
+        assert!(lo <= hi, "lower bound of the interval must not exceed the upper bound");
+        if self.value.get() == (lo, hi) {
+            return;
+        }
+        next_generation();
+        self.invalidate();
+        self.value.set((lo, hi));
+    }
+}
+
+impl<'a> Node for IntervalInput<'a> {
+    type Output = Interval;
+
+    fn compute(&self) -> Interval {
+        self.value.get()
+    }
+
+    fn invalidate(&self) {
+        self.dep.invalidate();
+    }
+
+    fn add_dependent(&self, n: Rc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn take_dependents(&self) -> Vec<Weak<dyn Dependent>> {
+        self.dep.take()
+    }
+
+    fn restore_dependents(&self, deps: Vec<Weak<dyn Dependent>>) {
+        self.dep.restore(deps);
+    }
+
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn is_input(&self) -> bool {
+        true
+    }
+}
+
+/// Operation node on the intervals with caching of the resulting interval.
+struct IntervalOp {
+    label: &'static str,
+    xs: Vec<Rc<dyn Node<Output = Interval>>>,
+    op: fn(&[Interval]) -> Interval,
+    cached: Cache<Interval>,
+    dep: Dependencies
+}
+
+impl IntervalOp {
+    fn new(label: &'static str, xs: Vec<Rc<dyn Node<Output = Interval>>>, op: fn(&[Interval]) -> Interval) -> Rc<Self> {
+        let tmp = Rc::new(IntervalOp { label, xs: xs.clone(), op, cached: Cache::new(), dep: Default::default() });
+        for x in xs.iter() {
+            x.add_dependent(tmp.clone());
+        }
+        tmp
+    }
+}
+
+impl Node for IntervalOp {
+    type Output = Interval;
+
+    fn compute(&self) -> Interval {
+        self.cached.get_or_else(|| {
+            let values: Vec<Interval> = self.xs.iter().map(|x| x.compute()).collect();
+            (self.op)(&values)
+        })
+    }
+
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn children(&self) -> Vec<Rc<dyn Node<Output = Interval>>> {
+        self.xs.clone()
+    }
+}
+
+/// Checks whether the interval contains a point `p + 2 * PI * k` for some integer `k`.
+fn contains_periodic((lo, hi): Interval, p: f32) -> bool {
+    let k = ((lo - p) / (2.0 * PI)).ceil();
+    p + 2.0 * PI * k <= hi
+}
+
+/// Creates interval input node of the compute graph with a given name.
+pub fn interval_input(name: &str) -> Rc<IntervalInput<'_>> {
+    Rc::new(IntervalInput::new(name))
+}
+
+/// Creates node of the sum of two intervals: `[a + c, b + d]`.
+pub fn iadd(x: Rc<dyn Node<Output = Interval>>, y: Rc<dyn Node<Output = Interval>>) -> Rc<dyn Node<Output = Interval>> {
+    IntervalOp::new("iadd", vec![x, y], |xs| (xs[0].0 + xs[1].0, xs[0].1 + xs[1].1))
+}
+
+/// Creates node of the product of two intervals, that is the range of the products of their bounds,
+/// so any combination of the signs of the bounds is handled.
+pub fn imul(x: Rc<dyn Node<Output = Interval>>, y: Rc<dyn Node<Output = Interval>>) -> Rc<dyn Node<Output = Interval>> {
+    IntervalOp::new("imul", vec![x, y], |xs| {
+        let ((a, b), (c, d)) = (xs[0], xs[1]);
+        let p = [a * c, a * d, b * c, b * d];
+        (p.iter().cloned().fold(f32::INFINITY, f32::min), p.iter().cloned().fold(f32::NEG_INFINITY, f32::max))
+    })
+}
+
+/// Creates node of the sinus of an interval. The result is the range of the sinus values at the bounds,
+/// extended to `1` or `-1` if the interval contains a maximum or a minimum of the sinus.
+pub fn isin(x: Rc<dyn Node<Output = Interval>>) -> Rc<dyn Node<Output = Interval>> {
+    IntervalOp::new("isin", vec![x], |xs| {
+        let (a, b) = xs[0];
+        let (sa, sb) = (a.sin(), b.sin());
+        let lo = if contains_periodic(xs[0], -FRAC_PI_2) { -1.0 } else { sa.min(sb) };
+        let hi = if contains_periodic(xs[0], FRAC_PI_2) { 1.0 } else { sa.max(sb) };
+        (lo, hi)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation;
+
+    #[test]
+    fn test_iadd() {
+        let x = interval_input("x");
+        let y = interval_input("y");
+        let sum = iadd(x.clone(), y.clone());
+        x.set(1.0, 2.0);
+        y.set(3.0, 4.0);
+        assert_eq!(sum.compute(), (4.0, 6.0));
+        y.set(-1.0, 0.0);
+        assert_eq!(sum.compute(), (0.0, 2.0));
+    }
+
+    #[test]
+    fn test_interval_input_set() {
+        let x = interval_input("x");
+        let sum = iadd(x.clone(), x.clone());
+        x.set(1.0, 2.0);
+        let before = generation();
+        assert_eq!(sum.compute(), (2.0, 4.0));
+        x.set(1.0, 2.0);
+        assert_eq!(generation(), before);
+        assert_eq!(sum.compute(), (2.0, 4.0));
+        assert_eq!(sum.recompute_count(), 1);
+        x.set(0.0, 2.0);
+        assert_eq!(generation(), before + 1);
+        assert_eq!(sum.compute(), (0.0, 4.0));
+    }
+
+    #[test]
+    fn test_imul_signs() {
+        let x = interval_input("x");
+        let y = interval_input("y");
+        let prod = imul(x.clone(), y.clone());
+        x.set(1.0, 2.0);
+        y.set(3.0, 4.0);
+        assert_eq!(prod.compute(), (3.0, 8.0));
+        y.set(-4.0, -3.0);
+        assert_eq!(prod.compute(), (-8.0, -3.0));
+        x.set(-1.0, 2.0);
+        assert_eq!(prod.compute(), (-8.0, 4.0));
+        y.set(-3.0, 5.0);
+        assert_eq!(prod.compute(), (-6.0, 10.0));
+    }
+
+    #[test]
+    fn test_isin() {
+        let x = interval_input("x");
+        let s = isin(x.clone());
+        x.set(0.0, 1.0);
+        assert_eq!(s.compute(), (0.0, 1f32.sin()));
+        x.set(1.0, 2.0);
+        assert_eq!(s.compute(), (1f32.sin(), 1.0));
+        x.set(3.0, 5.0);
+        assert_eq!(s.compute(), (-1.0, 3f32.sin()));
+        x.set(0.0, 7.0);
+        assert_eq!(s.compute(), (-1.0, 1.0));
+    }
+}
@@ -37,20 +37,50 @@ pub mod node;
 pub mod cache;
 pub mod utils;
 pub mod operations;
+pub mod context;
+pub mod typed;
+pub mod analysis;
+pub mod state;
+pub mod record;
+pub mod pattern;
+pub mod optimize;
+pub mod display;
+pub mod interval;
+pub mod pipeline;
+pub mod expr;
+pub mod sync;
+pub mod dynamic;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod readonly;
+pub mod scalar;
+pub mod history;
 
 pub use utils::*;
-pub use node::Node;
+pub use node::{Node, on_invalidate, clear_invalidate_callbacks, generation};
+pub use context::{GraphContext, SessionStats};
+pub use readonly::ReadOnlyGraph;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // round to decimal digits
-    fn round(x: f32, precision: u32) -> f32 {
+    pub(crate) fn round(x: f32, precision: u32) -> f32 {
         let m = 10i32.pow(precision) as f32;
         (x * m).round() / m
     }
 
+    /// Graph of the example `x1 + x2 * sin(x2 + x3 ^ 3)` from the README with the inputs set to `1`, `2` and `3`.
+    pub(crate) fn readme_graph() -> (std::rc::Rc<dyn Node<Output = f32>>, [std::rc::Rc<operations::Input<'static>>; 3]) {
+        let [x1, x2, x3] = ["x1", "x2", "x3"].map(create_input);
+        let graph = add(x1.clone(), mul(x2.clone(), sin(add(x2.clone(), pow_f32(x3.clone(), 3f32)))));
+        x1.set(1.0);
+        x2.set(2.0);
+        x3.set(3.0);
+        (graph, [x1, x2, x3])
+    }
+
     #[test]
     fn test_input_ref() {
         let x1 = create_input("x1");
@@ -122,4 +152,116 @@ mod tests {
         println!("Graph output = {}", result);
         assert_eq!(round(result, 5), -0.56656);
     }
+
+    #[test]
+    fn test_recompute_count() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let unaffected = sin(x1.clone());
+        let affected = mul(x2.clone(), x2.clone());
+        let graph = add(unaffected.clone(), affected.clone());
+        graph.compute();
+        assert_eq!(unaffected.recompute_count(), 1);
+        assert_eq!(affected.recompute_count(), 1);
+        x2.set(3.0);
+        assert_eq!(graph.compute(), 9.0);
+        assert_eq!(unaffected.recompute_count(), 1);
+        assert_eq!(affected.recompute_count(), 2);
+        assert_eq!(graph.recompute_count(), 2);
+        graph.reset_recompute_count();
+        assert_eq!(graph.recompute_count(), 0);
+    }
+
+    #[test]
+    fn test_invalidate_callback() {
+        use std::cell::RefCell;
+        use std::collections::HashSet;
+        use std::rc::Rc;
+
+        let x = create_input("x");
+        let a = sin(x.clone());
+        let b = mul(x.clone(), x.clone());
+        let graph = add(a.clone(), b.clone());
+        graph.compute();
+        let ids = Rc::new(RefCell::new(HashSet::new()));
+        {
+            let ids = ids.clone();
+            on_invalidate(Box::new(move |id| { ids.borrow_mut().insert(id); }));
+        }
+        x.set(1.0);
+        clear_invalidate_callbacks();
+        assert_eq!(*ids.borrow(), HashSet::from([a.id(), b.id(), graph.id()]));
+        x.set(2.0);
+        assert_eq!(ids.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_invalidate_diamonds() {
+        let x = create_input("x");
+        let mut graph: std::rc::Rc<dyn Node<Output = f32>> = x.clone();
+        for _ in 0..20 {
+            graph = add(graph.clone(), graph.clone());
+        }
+        x.set(1.0);
+        assert_eq!(graph.compute(), (1 << 20) as f32);
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        {
+            let count = count.clone();
+            on_invalidate(Box::new(move |_| count.set(count.get() + 1)));
+        }
+        x.set(0.5);
+        clear_invalidate_callbacks();
+        // each node is reached by 2^k paths and visited once
+        assert_eq!(count.get(), 20);
+        assert_eq!(graph.compute(), (1 << 19) as f32);
+    }
+
+    #[test]
+    fn test_dropped_dependents() {
+        let x = create_input("x");
+        let _kept = sin(x.clone());
+        for i in 0..1000 {
+            let graph = mul(x.clone(), sin(x.clone()));
+            x.set(i as f32);
+            graph.compute();
+        }
+        let deps = x.take_dependents();
+        assert!(deps.len() < 64, "{}", deps.len());
+        x.restore_dependents(deps);
+        x.set(-1.0);
+        assert_eq!(x.take_dependents().len(), 1);
+    }
+
+    #[test]
+    fn test_is_constant() {
+        let x1 = create_input("x1");
+        assert!(add(constant(1.0), constant(2.0)).is_constant());
+        assert!(!add(x1.clone(), constant(2.0)).is_constant());
+        assert!(sin(mul(constant(1.0), constant(2.0))).is_constant());
+        assert!(!x1.is_constant());
+    }
+
+    #[test]
+    fn test_f64_graph() {
+        let x = create_input::<f64>("x");
+        let y = create_input::<f64>("y");
+        let graph = add(mul(x.clone(), y.clone()), constant(1e-12));
+        x.set(1.0 + 1e-9);
+        y.set(3.0);
+        assert_eq!(graph.compute(), (1.0 + 1e-9) * 3.0 + 1e-12);
+        // the difference is lost in f32
+        assert!((graph.compute() - 3.0).abs() > 1e-9);
+    }
+
+    #[test]
+    fn test_i64_graph() {
+        let x = create_input::<i64>("x");
+        let y = create_input::<i64>("y");
+        let graph = sub(mul(x.clone(), y.clone()), div(x.clone(), constant(2)));
+        x.set(1 << 40);
+        y.set(3);
+        assert_eq!(graph.compute(), (3 << 40) - (1 << 39));
+        y.set(-1);
+        assert_eq!(graph.compute(), -(1 << 40) - (1 << 39));
+    }
 }
@@ -1,7 +1,11 @@
 //! Basic trait and types to create computational graph with caching.
 
+use std::any::Any;
 use std::rc::{Rc, Weak};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+
+/// Partial derivatives of an operation on two values with respect to each of them.
+pub type Partials<T> = fn(T, T) -> [T; 2];
 
 /// Node trait represent a compute graph node that can return a (cached) value, get call for invalidation
 /// and get link to another node dependent on the current and so its cache must be invaludated
@@ -40,8 +44,6 @@ use std::cell::RefCell;
 /// println!("Graph output = {}", result);
 /// assert_eq!(round(result, 5), -0.56656);
 /// ```
-/// 
-
 pub trait Node {
     type Output;
 
@@ -51,28 +53,257 @@ pub trait Node {
     /// Invalidate the cache of the current node and the dependent nodes.
     fn invalidate(&self);
     /// Add some node n to the list of the nodes that are dependent of the value of this node.
-    fn add_dependent(&self, n: Rc<dyn Node<Output = Self::Output>>); 
+    fn add_dependent(&self, n: Rc<dyn Dependent>); 
+    /// Remove all the dependent nodes from the list of the node and return them.
+    fn take_dependents(&self) -> Vec<Weak<dyn Dependent>> {
+        Vec::new()
+    }
+    /// Add previously taken dependent nodes back to the list of the node.
+    fn restore_dependents(&self, _deps: Vec<Weak<dyn Dependent>>) {}
+    /// Returns the label of the node: the name of the operation or the name of the input.
+    fn label(&self) -> String {
+        String::from("node")
+    }
+    /// Returns the identity of the node, that is the address of the node in the memory.
+    fn id(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+    /// Returns `true` for the input nodes, whose values are set from the outside of the graph.
+    fn is_input(&self) -> bool {
+        false
+    }
+    /// Returns the value of the node if it is a constant node, or `None` otherwise.
+    fn constant_value(&self) -> Option<Self::Output> {
+        None
+    }
+    /// Returns the input nodes of the current node, i.e. the nodes its value is computed from.
+    fn children(&self) -> Vec<Rc<dyn Node<Output = Self::Output>>> {
+        Vec::new()
+    }
+    /// Returns `true` if the value of the node does not depend on the inputs: for the constant nodes and for the
+    /// operations on the constant nodes only. The other nodes without children, e.g. the inputs, are not constant.
+    fn is_constant(&self) -> bool {
+        let children = self.children();
+        if children.is_empty() {
+            self.constant_value().is_some()
+        } else {
+            children.iter().all(|c| c.is_constant())
+        }
+    }
+    /// Returns the derivative of the operation of a single-variable node, if the operation knows it.
+    fn derivative(&self) -> Option<fn(Self::Output) -> Self::Output> {
+        None
+    }
+    /// Returns the partial derivatives of the operation of a two-variable node, if the operation knows them.
+    fn partials(&self) -> Option<Partials<Self::Output>> {
+        None
+    }
+    /// Returns the number of the recomputations of the node value (the cache misses)
+    /// since the node creation or the last reset. Nodes without cache return zero.
+    fn recompute_count(&self) -> u64 {
+        0
+    }
+    /// Reset the counter of the recomputations of the node value.
+    fn reset_recompute_count(&self) {}
+    /// Returns the number of the requests of the node value served from the cache. Nodes without cache return zero.
+    fn cache_hits(&self) -> u64 {
+        0
+    }
+    /// Returns `true` if the node has a valid cached value.
+    fn is_cached(&self) -> bool {
+        false
+    }
+    /// Returns the input generation (see [`generation`]) of the last recomputation of the cached value,
+    /// or `None` for the nodes without cache.
+    fn computed_at(&self) -> Option<u64> {
+        None
+    }
+    /// Enable or disable caching of the node value. The node without caching recomputes its value on every request.
+    fn set_caching(&self, _enabled: bool) {}
+    /// Returns `true` if the node stores its computed value, i.e. it has cache and the caching is not disabled.
+    fn is_caching(&self) -> bool {
+        false
+    }
+    /// Returns the priority of the refresh of the node by [`compute_budgeted`](crate::analysis::compute_budgeted).
+    /// The nodes without cache have the priority `0`.
+    fn priority(&self) -> u8 {
+        0
+    }
+    /// Sets the priority of the refresh of the node, ignored by the nodes without cache.
+    fn set_priority(&self, _p: u8) {}
+    /// Returns a copy of the internal state of a stateful node, or `None` for the nodes without state.
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+    /// Replace the internal state of a stateful node with previously saved `state`.
+    fn load_state(&self, _state: &dyn Any) {}
+}
+
+/// Dependent is the part of the node interface that does not depend on the output type of the node.
+/// It allows the nodes to depend on the nodes with another output type, e.g. a scalar node on a vector node.
+pub trait Dependent {
+    /// Invalidate the cache of the dependent node and its own dependent nodes.
+    fn invalidate_dependent(&self);
+}
+
+impl<N: Node + ?Sized> Dependent for N {
+    fn invalidate_dependent(&self) {
+        self.invalidate();
+    }
+}
+
+/// Callback called with the id of the invalidated node.
+pub type InvalidateCallback = Box<dyn Fn(usize)>;
+
+thread_local! {
+    static INVALIDATE_CALLBACKS: RefCell<Vec<InvalidateCallback>> = RefCell::new(Vec::new());
+}
+
+/// Register callback `f` that is called with the id of every node whose cache is invalidated.
+/// Callbacks are shared by all the graphs of the current thread, e.g. to mark dirty the external resources
+/// corresponding to the nodes. Callbacks must not register other callbacks.
+pub fn on_invalidate(f: InvalidateCallback) {
+    INVALIDATE_CALLBACKS.with(|c| c.borrow_mut().push(f));
+}
+
+/// Remove all the invalidation callbacks of the current thread.
+pub fn clear_invalidate_callbacks() {
+    INVALIDATE_CALLBACKS.with(|c| c.borrow_mut().clear());
+}
+
+/// Call the invalidation callbacks for the node with a given id.
+pub(crate) fn notify_invalidate(id: usize) {
+    INVALIDATE_CALLBACKS.with(|c| {
+        for f in c.borrow().iter() {
+            f(id);
+        }
+    });
+}
+
+thread_local! {
+    /// Counter of the invalidation passes of the current thread.
+    static EPOCH: Cell<u64> = const { Cell::new(0) };
+    /// Depth of the nested invalidation calls of the current pass.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Run `f` as a single invalidation pass, so the nodes reachable from several invalidated nodes
+/// are visited once.
+pub(crate) fn invalidation_pass(f: impl FnOnce()) {
+    EPOCH.set(EPOCH.get() + 1);
+    let _depth = DepthGuard::enter();
+    f();
+}
+
+/// Nesting level of the invalidation calls, that is left also when a callback panics,
+/// so the next invalidation on the thread starts a new pass.
+struct DepthGuard;
+
+impl DepthGuard {
+    fn enter() -> Self {
+        DEPTH.set(DEPTH.get() + 1);
+        DepthGuard
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.set(DEPTH.get() - 1);
+    }
+}
+
+thread_local! {
+    static GENERATION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns the input generation of the current thread, that is the number of the changes of the input values.
+pub fn generation() -> u64 {
+    GENERATION.with(|g| g.get())
+}
+
+/// Start new input generation on the change of an input value.
+pub(crate) fn next_generation() {
+    GENERATION.with(|g| g.set(g.get() + 1));
 }
 
 /// Dependencies contain links to the dependent nodes that must be invalidated and recomputed once the value
 /// of the current node changes. 
 #[derive(Default)]
-pub(crate) struct Dependencies<T> {
-    vec: RefCell<Vec<Weak<dyn Node<Output = T>>>>
+pub(crate) struct Dependencies {
+    vec: RefCell<Vec<Weak<dyn Dependent>>>,
+    // The last invalidation pass that visited the dependent nodes.
+    epoch: Cell<u64>
 }
 
-impl<T> Dependencies<T> {
-    pub(crate) fn add(&self, n: Rc<dyn Node<Output = T>>) {
+impl Dependencies {
+    pub(crate) fn add(&self, n: Rc<dyn Dependent>) {
+        let mut vec = self.vec.borrow_mut();
+        // The links to the dropped nodes are removed before the growth of the vector,
+        // so its length stays proportional to the number of the live dependent nodes.
+        if vec.len() == vec.capacity() {
+            vec.retain(|d| d.strong_count() > 0);
+        }
         // Rc are downgraded to Weak to prevent the occurrence of cyclic dependencies.
-        self.vec.borrow_mut().push(Rc::downgrade(&n));
+        vec.push(Rc::downgrade(&n));
+    }
+
+    pub(crate) fn take(&self) -> Vec<Weak<dyn Dependent>> {
+        self.vec.take()
+    }
+
+    pub(crate) fn restore(&self, deps: Vec<Weak<dyn Dependent>>) {
+        self.vec.borrow_mut().extend(deps);
     }
 
     pub(crate) fn invalidate(&self) {
+        self.invalidate_with(|| {});
+    }
+
+    /// Invalidate the node owning the dependencies with `f` and then its dependent nodes.
+    /// The node reachable by several paths is visited by the invalidation pass once: the outermost call
+    /// starts a new pass, and the node already visited by the current pass is skipped.
+    pub(crate) fn invalidate_with(&self, f: impl FnOnce()) {
+        if DEPTH.get() == 0 {
+            EPOCH.set(EPOCH.get() + 1);
+        }
+        if self.epoch.get() == EPOCH.get() {
+            return;
+        }
+        self.epoch.set(EPOCH.get());
+        f();
+        let _depth = DepthGuard::enter();
+        let mut dropped = false;
         for d in self.vec.borrow().iter() {
-            if let Some(x) = d.upgrade() {
-                 x.invalidate()
-                }
+            match d.upgrade() {
+                Some(x) => x.invalidate_dependent(),
+                None => dropped = true
+            }
+        }
+        // The links to the dropped nodes are removed after the iteration, as the dependent nodes are not
+        // borrowed anymore.
+        if dropped {
+            self.vec.borrow_mut().retain(|d| d.strong_count() > 0);
         }
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{add, create_input, sin};
+
+    #[test]
+    fn test_panicking_callback_ends_pass() {
+        let x = create_input("x");
+        let graph = add(sin(x.clone()), x.clone());
+        assert_eq!(graph.compute(), 0.0);
+        on_invalidate(Box::new(|_| panic!("callback failed")));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| x.set(1.0)));
+        clear_invalidate_callbacks();
+        assert!(res.is_err());
+        assert_eq!(DEPTH.get(), 0);
+        x.set(2.0);
+        assert_eq!(graph.compute(), 2f32.sin() + 2.0);
+    }
+}
@@ -1,36 +1,155 @@
 //! Provides some operations as building blocks to create computational graph.
 
-use crate::node::{Node, Dependencies};
-use crate::cache::Cache;
+use crate::node::{Node, Dependent, Dependencies, Partials, next_generation};
+use crate::cache::{Cache, cached_node_methods};
+use crate::record::Recorder;
 
-use std::cell::Cell;
-use std::rc::Rc;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
 
-/// Input node present some f32 input value. This node stores a list of dependent nodes `dep`
+/// Input node present some input value of type `T` (`f32` by default). This node stores a list of dependent nodes `dep`
 /// and invalidates their caches when the input values is changed.
-pub struct Input<'a> {
-    _name: &'a str,
-    value: Cell<f32>,
-    dep: Dependencies<f32>
+/// An input can follow another input (`target`), in which case it reads and writes the value of that input.
+/// The changes of the input value can be reported to the `recorder`.
+pub struct Input<'a, T: Copy = f32> {
+    name: &'a str,
+    value: Cell<T>,
+    target: RefCell<Option<Rc<Input<'a, T>>>>,
+    recorder: RefCell<Option<(Weak<Recorder<'a, T>>, usize)>>,
+    dep: Dependencies
 }
 
-impl<'a> Input<'a> {
-    pub fn new(_name: &'a str) -> Input<'a>{
-        Input { _name, value: Default::default(), dep: Default::default() }
+impl<'a, T: Copy + Default> Input<'a, T> {
+    pub fn new(name: &'a str) -> Input<'a, T>{
+        Input {
+            name,
+            value: Default::default(),
+            target: Default::default(),
+            recorder: Default::default(),
+            dep: Default::default()
+        }
     }
+}
 
+impl<'a, T: Copy + PartialEq> Input<'a, T> {
     /// Set new value `x` and require invalidation of the caches of the dependent nodes.
-    pub fn set(&self, x: f32) {
+    /// Setting the value equal (`==`) to the current one is skipped, so the caches are kept.
+    /// For `f32` it means that NaN is always treated as a new value, and `-0.0` over `0.0`
+    /// (or vice versa) as the same value, so the input keeps the previous zero.
+    pub fn set(&self, x: T) {
+        if let Some(t) = self.target.borrow().as_ref() {
+            return t.set(x);
+        }
+        if self.value.get() == x {
+            return;
+        }
+        // Only the actual changes are recorded, by the input that stores the value.
+        if let Some((r, i)) = self.recorder.borrow().as_ref() {
+            if let Some(r) = r.upgrade() {
+                r.record(*i, x);
+            }
+        }
+        next_generation();
         self.invalidate();
         self.value.set(x);
     }
 }
 
-impl<'a> Node for Input<'a> {
-    type Output = f32;
+impl<'a, T: Copy> Input<'a, T> {
+    /// Report the subsequent changes of the value to the `recorder` as the changes of its input with index `i`.
+    pub(crate) fn record_to(&self, recorder: Weak<Recorder<'a, T>>, i: usize) {
+        self.recorder.replace(Some((recorder, i)));
+    }
+}
+
+impl<T: Copy + 'static> Input<'static, T> {
+    /// Make this input follow `target`: its value is read from `target`, `set` is forwarded to it
+    /// and the changes of `target` invalidate the dependent nodes of this input.
+    pub(crate) fn follow(self: &Rc<Self>, target: Rc<Input<'static, T>>) {
+        target.add_dependent(self.clone());
+        self.target.replace(Some(target));
+        self.invalidate();
+    }
+}
+
+impl<'a, T: Copy> Node for Input<'a, T> {
+    type Output = T;
 
     fn compute(&self) -> Self::Output {
-        self.value.get()
+        match self.target.borrow().as_ref() {
+            Some(t) => t.compute(),
+            None => self.value.get()
+        }
+    }
+
+    /// Require invalidation of the dependent nodes.
+    fn invalidate(&self) {
+        self.dep.invalidate();
+    }
+
+    fn add_dependent(&self, n: Rc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn take_dependents(&self) -> Vec<Weak<dyn Dependent>> {
+        self.dep.take()
+    }
+
+    fn restore_dependents(&self, deps: Vec<Weak<dyn Dependent>>) {
+        self.dep.restore(deps);
+    }
+
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn is_input(&self) -> bool {
+        true
+    }
+}
+
+/// Slice input node present the input value stored at `index` of the `buffer` shared by several inputs.
+/// The value written to the buffer directly is seen by the dependent nodes after [`SliceInput::sync`].
+pub struct SliceInput<'a> {
+    name: &'a str,
+    buffer: Rc<RefCell<Vec<f32>>>,
+    index: usize,
+    synced: Cell<f32>,
+    dep: Dependencies
+}
+
+impl<'a> SliceInput<'a> {
+    pub fn new(buffer: Rc<RefCell<Vec<f32>>>, index: usize, name: &'a str) -> Self {
+        let synced = Cell::new(buffer.borrow()[index]);
+        SliceInput { name, buffer, index, synced, dep: Default::default() }
+    }
+
+    /// Set new value `x` to the buffer and require invalidation of the caches of the dependent nodes.
+    /// Setting the value equal to the current one is skipped like for [`Input::set`].
+    pub fn set(&self, x: f32) {
+        self.buffer.borrow_mut()[self.index] = x;
+        self.sync();
+    }
+
+    /// Require invalidation of the caches of the dependent nodes if the value in the buffer
+    /// has changed since the last synchronization.
+    pub fn sync(&self) {
+        let x = self.buffer.borrow()[self.index];
+        if self.synced.get() == x {
+            return;
+        }
+        next_generation();
+        self.invalidate();
+        self.synced.set(x);
+    }
+}
+
+impl<'a> Node for SliceInput<'a> {
+    type Output = f32;
+
+    fn compute(&self) -> f32 {
+        self.buffer.borrow()[self.index]
     }
 
     /// Require invalidation of the dependent nodes.
@@ -38,90 +157,398 @@ impl<'a> Node for Input<'a> {
         self.dep.invalidate();
     }
 
-    fn add_dependent(&self, n: Rc<dyn Node<Output = Self::Output>>) {
+    fn add_dependent(&self, n: Rc<dyn Dependent>) {
         self.dep.add(n);
     }
+
+    fn take_dependents(&self) -> Vec<Weak<dyn Dependent>> {
+        self.dep.take()
+    }
+
+    fn restore_dependents(&self, deps: Vec<Weak<dyn Dependent>>) {
+        self.dep.restore(deps);
+    }
+
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn is_input(&self) -> bool {
+        true
+    }
+}
+
+/// Constant node present some fixed value of type `T` (`f32` by default). It never changes, so it does not store dependent nodes.
+pub struct Constant<T: Copy = f32> {
+    value: T
+}
+
+impl<T: Copy> Constant<T> {
+    pub fn new(value: T) -> Constant<T> {
+        Constant { value }
+    }
+}
+
+impl<T: Copy + ToString> Node for Constant<T> {
+    type Output = T;
+
+    fn compute(&self) -> T {
+        self.value
+    }
+
+    /// Constant has no cache and its value never changes, so there is nothing to invalidate.
+    fn invalidate(&self) {}
+
+    fn add_dependent(&self, _n: Rc<dyn Dependent>) {}
+
+    fn label(&self) -> String {
+        self.value.to_string()
+    }
+
+    fn constant_value(&self) -> Option<T> {
+        Some(self.value)
+    }
 }
 
 /// Unary type of Node takes a single inputs nodes (`x`) and operation (`op`) as Fn. This type provides caching
 /// of the computations and invaludation of its cache and dependent nodes.
-pub struct Unary<T: Fn(f32) -> f32> {
-    x: Rc<dyn Node<Output = f32>>,
-    op: T,
-    cached: Cache<f32>,
-    dep: Dependencies<f32>
+/// The operation can optionally know its derivative (`deriv`).
+pub struct Unary<F: Fn(T) -> T, T: Copy = f32> {
+    label: &'static str,
+    x: Rc<dyn Node<Output = T>>,
+    op: F,
+    deriv: Option<fn(T) -> T>,
+    cached: Cache<T>,
+    dep: Dependencies
 }
 
-impl<T: Fn(f32) -> f32 + 'static> Unary<T> {
-    pub fn new(x: Rc<dyn Node<Output = f32>>, op: T) -> Rc<Self> {
+impl<F: Fn(T) -> T + 'static, T: Copy + 'static> Unary<F, T> {
+    pub fn new(x: Rc<dyn Node<Output = T>>, op: F) -> Rc<Self> {
+        Self::named("unary", x, op)
+    }
+
+    /// Create new unary node with a given label of the operation.
+    pub fn named(label: &'static str, x: Rc<dyn Node<Output = T>>, op: F) -> Rc<Self> {
         // Create new unary node
         let tmp = Rc::new( 
-            Self { x: x.clone(), op, dep: Default::default(), cached: Cache::new() } 
+            Self { label, x: x.clone(), op, deriv: None, dep: Default::default(), cached: Cache::new() } 
         );
         // Add the new node to the list of dependent nodes.
         x.add_dependent(tmp.clone());
         tmp
     }
+
+    /// Create new unary node with a given label of the operation and the derivative `deriv` of the operation.
+    pub fn with_derivative(label: &'static str, x: Rc<dyn Node<Output = T>>, op: F, deriv: fn(T) -> T) -> Rc<Self> {
+        let tmp = Rc::new(
+            Self { label, x: x.clone(), op, deriv: Some(deriv), dep: Default::default(), cached: Cache::new() }
+        );
+        x.add_dependent(tmp.clone());
+        tmp
+    }
 }
 
-impl<T: Fn(f32) -> f32> Node for Unary<T> {
-    type Output = f32;
+impl<F: Fn(T) -> T, T: Copy + 'static> Node for Unary<F, T> {
+    type Output = T;
 
     /// Get cached value or apply the operation to the input.
-    fn compute(&self) -> f32 {
+    fn compute(&self) -> T {
         self.cached.get_or_else(|| (self.op)(self.x.compute()) )
     }
 
-    /// Invalidate its own cache and then invalidate the dependent nodes.
-    fn invalidate(&self) {
-        self.cached.invalidate();
-        self.dep.invalidate();
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
     }
 
-    fn add_dependent(&self, n: Rc<dyn Node<Output = f32>>) {
-        self.dep.add(n);
+    fn children(&self) -> Vec<Rc<dyn Node<Output = T>>> {
+        vec![self.x.clone()]
+    }
+
+    fn derivative(&self) -> Option<fn(T) -> T> {
+        self.deriv
     }
 }
 
 
+/// Map type of Node takes a single input node (`x`) and operation (`op`) converting its value to the value
+/// of another type, e.g. a scalar to a vector. This type provides caching of the computations and invalidation
+/// of its cache and dependent nodes. The input has another value type, so it is not listed in `children`:
+/// the traversals of the graph, e.g. [`nodes_of`](crate::analysis::nodes_of), [`edges`](crate::analysis::edges),
+/// [`print_tree`](crate::analysis::print_tree), [`to_dot`](crate::display::to_dot),
+/// [`compute_checked`](crate::analysis::compute_checked) and [`compute_budgeted`](crate::analysis::compute_budgeted),
+/// stop at the map node and do not visit the nodes it depends on.
+pub struct Map<F: Fn(I) -> O, I, O: Clone> {
+    label: &'static str,
+    x: Rc<dyn Node<Output = I>>,
+    op: F,
+    cached: Cache<O>,
+    dep: Dependencies
+}
+
+impl<F: Fn(I) -> O + 'static, I: 'static, O: Clone + 'static> Map<F, I, O> {
+    /// Create new map node with a given label of the operation.
+    pub fn named(label: &'static str, x: Rc<dyn Node<Output = I>>, op: F) -> Rc<Self> {
+        let tmp = Rc::new(Self { label, x: x.clone(), op, dep: Default::default(), cached: Cache::new() });
+        x.add_dependent(tmp.clone());
+        tmp
+    }
+}
+
+impl<F: Fn(I) -> O, I, O: Clone + 'static> Node for Map<F, I, O> {
+    type Output = O;
+
+    fn compute(&self) -> O {
+        self.cached.get_or_else(|| (self.op)(self.x.compute()))
+    }
+
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+}
+
 /// Binary type of Node takes two inputs nodes (`x` and `y`) and operation (`op`) on them. 
 /// This type provides caching of the computations and invaludation of its cache and dependent nodes.
-pub struct Binary<T: Fn(f32,f32) -> f32> {
-    x: Rc<dyn Node<Output = f32>>,
-    y: Rc<dyn Node<Output = f32>>,
-    op: T,
-    cached: Cache<f32>,
-    dep: Dependencies<f32>
+/// The operation can optionally know its partial derivatives (`partials`).
+pub struct Binary<F: Fn(T,T) -> T, T: Copy = f32> {
+    label: &'static str,
+    x: Rc<dyn Node<Output = T>>,
+    y: Rc<dyn Node<Output = T>>,
+    op: F,
+    partials: Option<Partials<T>>,
+    cached: Cache<T>,
+    dep: Dependencies
 }
 
-impl<T: Fn(f32,f32) -> f32 + 'static> Binary<T> {
-    pub fn new(x: Rc<dyn Node<Output = f32>>, y: Rc<dyn Node<Output = f32>>, op: T) -> Rc<Self> {
+impl<F: Fn(T,T) -> T + 'static, T: Copy + 'static> Binary<F, T> {
+    pub fn new(x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>, op: F) -> Rc<Self> {
+        Self::named("binary", x, y, op)
+    }
+
+    /// Create new binary node with a given label of the operation.
+    pub fn named(label: &'static str, x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>, op: F) -> Rc<Self> {
         // Create new binary node
         let tmp = Rc::new(
-            Self { x: x.clone(), y: y.clone(), op, dep: Default::default(), cached: Cache::new() }
+            Self { label, x: x.clone(), y: y.clone(), op, partials: None, dep: Default::default(), cached: Cache::new() }
         );
         // Add a new node to the lists of the input nodes
         x.add_dependent(tmp.clone());
         y.add_dependent(tmp.clone());
         tmp
     }
+
+    /// Create new binary node with a given label of the operation and the partial derivatives `partials`
+    /// of the operation with respect to `x` and `y`.
+    pub fn with_partials(
+        label: &'static str, x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>, op: F, partials: Partials<T>
+    ) -> Rc<Self> {
+        let tmp = Rc::new(
+            Self { label, x: x.clone(), y: y.clone(), op, partials: Some(partials), dep: Default::default(), cached: Cache::new() }
+        );
+        x.add_dependent(tmp.clone());
+        y.add_dependent(tmp.clone());
+        tmp
+    }
 }
 
-impl<T: Fn(f32,f32) -> f32> Node for Binary<T> {
-    type Output = f32;
+impl<F: Fn(T,T) -> T, T: Copy + 'static> Node for Binary<F, T> {
+    type Output = T;
 
-    fn compute(&self) -> f32 {
+    fn compute(&self) -> T {
         // Get cached value or compute the result
         self.cached.get_or_else(|| (self.op)(self.x.compute(), self.y.compute()))
     }
 
-    fn invalidate(&self) {
-        self.cached.invalidate();
-        self.dep.invalidate();
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
     }
 
-    fn add_dependent(&self, n: Rc<dyn Node<Output = f32>>) {
-        self.dep.add(n);
+    fn children(&self) -> Vec<Rc<dyn Node<Output = T>>> {
+        vec![self.x.clone(), self.y.clone()]
+    }
+
+    fn partials(&self) -> Option<Partials<T>> {
+        self.partials
+    }
+}
+
+/// Ternary type of Node takes three inputs nodes (`x`, `y` and `z`) and operation (`op`) on them.
+/// This type provides caching of the computations and invaludation of its cache and dependent nodes.
+pub struct Ternary<F: Fn(T,T,T) -> T, T: Copy = f32> {
+    label: &'static str,
+    x: Rc<dyn Node<Output = T>>,
+    y: Rc<dyn Node<Output = T>>,
+    z: Rc<dyn Node<Output = T>>,
+    op: F,
+    cached: Cache<T>,
+    dep: Dependencies
+}
+
+impl<F: Fn(T,T,T) -> T + 'static, T: Copy + 'static> Ternary<F, T> {
+    pub fn new(x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>, z: Rc<dyn Node<Output = T>>, op: F) -> Rc<Self> {
+        Self::named("ternary", x, y, z, op)
+    }
+
+    /// Create new ternary node with a given label of the operation.
+    pub fn named(
+        label: &'static str,
+        x: Rc<dyn Node<Output = T>>,
+        y: Rc<dyn Node<Output = T>>,
+        z: Rc<dyn Node<Output = T>>,
+        op: F
+    ) -> Rc<Self> {
+        // Create new ternary node
+        let tmp = Rc::new(
+            Self { label, x: x.clone(), y: y.clone(), z: z.clone(), op, dep: Default::default(), cached: Cache::new() }
+        );
+        // Add a new node to the lists of the input nodes
+        x.add_dependent(tmp.clone());
+        y.add_dependent(tmp.clone());
+        z.add_dependent(tmp.clone());
+        tmp
+    }
+}
+
+impl<F: Fn(T,T,T) -> T, T: Copy + 'static> Node for Ternary<F, T> {
+    type Output = T;
+
+    fn compute(&self) -> T {
+        // Get cached value or compute the result
+        self.cached.get_or_else(|| (self.op)(self.x.compute(), self.y.compute(), self.z.compute()))
+    }
+
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn children(&self) -> Vec<Rc<dyn Node<Output = T>>> {
+        vec![self.x.clone(), self.y.clone(), self.z.clone()]
+    }
+}
+
+/// NAry type of Node takes a list of input nodes (`xs`) and operation (`op`) on the slice of their values.
+/// This type provides caching of the computations and invaludation of its cache and dependent nodes.
+pub struct NAry<F: Fn(&[T]) -> T, T: Copy = f32> {
+    label: &'static str,
+    xs: Vec<Rc<dyn Node<Output = T>>>,
+    op: F,
+    cached: Cache<T>,
+    dep: Dependencies
+}
+
+impl<F: Fn(&[T]) -> T + 'static, T: Copy + 'static> NAry<F, T> {
+    pub fn new(xs: Vec<Rc<dyn Node<Output = T>>>, op: F) -> Rc<Self> {
+        Self::named("nary", xs, op)
+    }
+
+    /// Create new n-ary node with a given label of the operation.
+    pub fn named(label: &'static str, xs: Vec<Rc<dyn Node<Output = T>>>, op: F) -> Rc<Self> {
+        // Create new n-ary node
+        let tmp = Rc::new(
+            Self { label, xs: xs.clone(), op, dep: Default::default(), cached: Cache::new() }
+        );
+        // Add a new node to the lists of all the input nodes
+        for x in xs.iter() {
+            x.add_dependent(tmp.clone());
+        }
+        tmp
+    }
+}
+
+impl<F: Fn(&[T]) -> T, T: Copy + 'static> Node for NAry<F, T> {
+    type Output = T;
+
+    fn compute(&self) -> T {
+        // Get cached value or compute the result on the values of all the inputs
+        self.cached.get_or_else(|| {
+            let values: Vec<T> = self.xs.iter().map(|x| x.compute()).collect();
+            (self.op)(&values)
+        })
+    }
+
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn children(&self) -> Vec<Rc<dyn Node<Output = T>>> {
+        self.xs.clone()
+    }
+}
+
+/// Stateful type of Node takes a list of input nodes (`xs`), an initial state (`state`) and an operation (`op`)
+/// that reads and updates the state on each recomputation. The state is kept between the computations,
+/// so the output depends on the history of the recomputations and not only on the current values of the inputs.
+/// Note that the operation runs only when the cache is invalid, i.e. once per change of the inputs.
+/// The output may have another type `O` than the inputs, e.g. the vector of the counts of the values,
+/// in that case the inputs are not listed in `children`, like the input of [`Map`].
+pub struct Stateful<S: Clone, F: Fn(&mut S, &[T]) -> O, T: Copy = f32, O: Clone = T> {
+    label: &'static str,
+    xs: Vec<Rc<dyn Node<Output = T>>>,
+    state: RefCell<S>,
+    op: F,
+    cached: Cache<O>,
+    dep: Dependencies
+}
+
+impl<S: Clone + 'static, F: Fn(&mut S, &[T]) -> O + 'static, T: Copy + 'static, O: Clone + 'static> Stateful<S, F, T, O> {
+    pub fn new(xs: Vec<Rc<dyn Node<Output = T>>>, state: S, op: F) -> Rc<Self> {
+        Self::named("stateful", xs, state, op)
+    }
+
+    /// Create new stateful node with a given label of the operation.
+    pub fn named(label: &'static str, xs: Vec<Rc<dyn Node<Output = T>>>, state: S, op: F) -> Rc<Self> {
+        // Create new stateful node
+        let tmp = Rc::new(
+            Self { label, xs: xs.clone(), state: RefCell::new(state), op, dep: Default::default(), cached: Cache::new().unverified() }
+        );
+        // Add a new node to the lists of all the input nodes
+        for x in xs.iter() {
+            x.add_dependent(tmp.clone());
+        }
+        tmp
+    }
+}
+
+impl<S: Clone + 'static, F: Fn(&mut S, &[T]) -> O, T: Copy + 'static, O: Clone + 'static> Node for Stateful<S, F, T, O> {
+    type Output = O;
+
+    fn compute(&self) -> O {
+        // Get cached value or update the state with the values of all the inputs
+        self.cached.get_or_else(|| {
+            let values: Vec<T> = self.xs.iter().map(|x| x.compute()).collect();
+            (self.op)(&mut self.state.borrow_mut(), &values)
+        })
+    }
+
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn children(&self) -> Vec<Rc<dyn Node<Output = O>>> {
+        // The inputs are the children only if they have the type of the output.
+        (&self.xs as &dyn Any).downcast_ref::<Vec<Rc<dyn Node<Output = O>>>>().cloned().unwrap_or_default()
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.state.borrow().clone()))
+    }
+
+    /// Replace the state of the node, if the `state` has the type of the node state.
+    fn load_state(&self, state: &dyn Any) {
+        if let Some(s) = state.downcast_ref::<S>() {
+            self.state.replace(s.clone());
+        }
     }
 }
 
@@ -140,6 +567,32 @@ mod tests {
         assert_eq!(input.compute(), 5.0);
     }
 
+    #[test]
+    fn test_input_same_value() {
+        let calls = Rc::new(Cell::new(0));
+        let input = create_input("x");
+        let graph = {
+            let calls = calls.clone();
+            Unary::new(input.clone(), move |x: f32| { calls.set(calls.get() + 1); x })
+        };
+        input.set(2.0);
+        graph.compute();
+        input.set(2.0);
+        assert_eq!(graph.compute(), 2.0);
+        assert_eq!(calls.get(), 1);
+        // zeros of different signs are equal, the previous zero is kept
+        input.set(0.0);
+        input.set(-0.0);
+        assert!(graph.compute().is_sign_positive());
+        assert_eq!(calls.get(), 2);
+        // NaN is never equal to itself, so it is always a new value
+        input.set(f32::NAN);
+        graph.compute();
+        input.set(f32::NAN);
+        graph.compute();
+        assert_eq!(calls.get(), 4);
+    }
+
     #[test]
     fn test_unary_op() {
         let flag = Rc::new(Cell::new(false));
@@ -215,4 +668,60 @@ mod tests {
         assert_eq!(node.compute(), 8.3);
         assert_eq!(flag.get(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_nary_op_caching() {
+        let flag = Rc::new(Cell::new(0));
+        let inputs: Vec<Rc<dyn Node<Output = f32>>> = vec![create_input("a"), create_input("b"), create_input("c")];
+        let node = {
+            let flag = flag.clone();
+            let f = move |xs: &[f32]| {
+                flag.set(flag.get() + 1);
+                xs.iter().sum()
+            };
+            NAry::new(inputs.clone(), f)
+        };
+        assert_eq!(node.compute(), 0.0);
+        assert_eq!(node.compute(), 0.0);
+        assert_eq!(flag.get(), 1);
+        inputs[2].invalidate();
+        assert_eq!(node.compute(), 0.0);
+        assert_eq!(flag.get(), 2);
+    }
+
+    #[test]
+    fn test_stateful_op() {
+        let input = create_input("foo");
+        let node = Stateful::new(vec![input.clone()], 0.0, |s: &mut f32, xs: &[f32]| { *s += xs[0]; *s });
+        input.set(1.0);
+        assert_eq!(node.compute(), 1.0);
+        assert_eq!(node.compute(), 1.0);
+        input.set(2.0);
+        assert_eq!(node.compute(), 3.0);
+        assert_eq!(node.children().len(), 1);
+        let counts = Stateful::new(vec![input.clone()], 0, |s: &mut usize, _: &[f32]| { *s += 1; vec![0.0; *s] });
+        assert_eq!(counts.compute().len(), 1);
+        assert!(counts.children().is_empty());
+    }
+
+    #[test]
+    fn test_ternary_op_caching() {
+        let flag = Rc::new(Cell::new(0));
+        let input = create_input("foo");
+        let node = {
+            let flag = flag.clone();
+            let f = move |x: f32, y: f32, z: f32| {
+                flag.set(flag.get() + 1);
+                x * y + z
+            };
+            Ternary::new(input.clone(), create_input("bar"), input.clone(), f)
+        };
+        input.set(2.0);
+        assert_eq!(node.compute(), 2.0);
+        assert_eq!(node.compute(), 2.0);
+        assert_eq!(flag.get(), 1);
+        input.set(3.0);
+        assert_eq!(node.compute(), 3.0);
+        assert_eq!(flag.get(), 2);
+    }
+}
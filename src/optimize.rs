@@ -0,0 +1,220 @@
+//! Optimization passes that rewrite the computational graph into an equivalent simpler graph.
+
+use crate::node::Node;
+use crate::pattern::Pattern;
+use crate::utils::{add, constant, div, mul, pow, sin, sub};
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Rule = (Pattern, fn(&[Rc<dyn Node<Output = f32>>]) -> Rc<dyn Node<Output = f32>>);
+
+/// Algebraic identities applied by [`simplify`].
+fn rules() -> Vec<Rule> {
+    use Pattern::{Any, Const, Op};
+    vec![
+        (Op("add", vec![Any, Const(0.0)]), |b| b[0].clone()),
+        (Op("add", vec![Const(0.0), Any]), |b| b[0].clone()),
+        (Op("sub", vec![Any, Const(0.0)]), |b| b[0].clone()),
+        (Op("mul", vec![Any, Const(1.0)]), |b| b[0].clone()),
+        (Op("mul", vec![Const(1.0), Any]), |b| b[0].clone()),
+        (Op("mul", vec![Any, Const(0.0)]), |_| constant(0.0)),
+        (Op("mul", vec![Const(0.0), Any]), |_| constant(0.0)),
+        (Op("pow", vec![Any, Const(1.0)]), |b| b[0].clone()),
+    ]
+}
+
+/// Create the node of the built-in operation with a given label on the new inputs.
+/// Returns `None` for the operations that can not be recreated from the label.
+pub(crate) fn rebuild(label: &str, c: &[Rc<dyn Node<Output = f32>>]) -> Option<Rc<dyn Node<Output = f32>>> {
+    match (label, c) {
+        ("add", [x, y]) => Some(add(x.clone(), y.clone())),
+        ("sub", [x, y]) => Some(sub(x.clone(), y.clone())),
+        ("mul", [x, y]) => Some(mul(x.clone(), y.clone())),
+        ("div", [x, y]) => Some(div(x.clone(), y.clone())),
+        ("pow", [x, y]) => Some(pow(x.clone(), y.clone())),
+        ("sin", [x]) => Some(sin(x.clone())),
+        _ => None
+    }
+}
+
+/// Returns `true` if the operation with a given label and number of inputs can be recreated by [`rebuild`].
+pub(crate) fn is_rebuildable(label: &str, arity: usize) -> bool {
+    matches!((label, arity), ("add" | "sub" | "mul" | "div" | "pow", 2) | ("sin", 1))
+}
+
+/// Apply the identities to a single node until none of them match.
+fn rewrite(mut node: Rc<dyn Node<Output = f32>>, rules: &[Rule]) -> Rc<dyn Node<Output = f32>> {
+    loop {
+        let children = node.children();
+        // x - x is not expressible as a pattern, since it requires both inputs to be the same node.
+        if let ("sub", [x, y]) = (node.label().as_str(), children.as_slice()) {
+            if Rc::ptr_eq(x, y) {
+                node = constant(0.0);
+                continue;
+            }
+        }
+        match rules.iter().find_map(|(p, f)| p.match_node(&node).map(|m| f(&m.bindings))) {
+            Some(n) => node = n,
+            None => return node
+        }
+    }
+}
+
+fn simplify_node(
+    node: &Rc<dyn Node<Output = f32>>,
+    rules: &[Rule],
+    done: &mut HashMap<usize, Rc<dyn Node<Output = f32>>>
+) -> Rc<dyn Node<Output = f32>> {
+    if let Some(n) = done.get(&node.id()) {
+        return n.clone();
+    }
+    let children = node.children();
+    let simplified: Vec<_> = children.iter().map(|c| simplify_node(c, rules, done)).collect();
+    let changed = children.iter().zip(simplified.iter()).any(|(c, s)| !Rc::ptr_eq(c, s));
+    let res = if changed {
+        rebuild(&node.label(), &simplified).map(|n| rewrite(n, rules)).unwrap_or_else(|| node.clone())
+    } else {
+        rewrite(node.clone(), rules)
+    };
+    done.insert(node.id(), res.clone());
+    res
+}
+
+/// Returns the graph equivalent to `root` with the algebraic identities `x + 0`, `x - 0`, `x * 1`, `x * 0`,
+/// `x - x` and `pow(x, 1)` folded. The unchanged nodes are reused, so the shared nodes stay shared.
+/// Only the built-in operations (`add`, `sub`, `mul`, `div`, `pow`, `sin`) are rebuilt when their inputs are simplified,
+/// the other operations are kept as they are together with their inputs.
+/// Note that folding `x * 0` and `x - x` to zero ignores the infinite and NaN values of `x`.
+pub fn simplify(root: &Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    simplify_node(root, &rules(), &mut HashMap::new())
+}
+
+fn clone_node(
+    node: &Rc<dyn Node<Output = f32>>,
+    done: &mut HashMap<usize, Rc<dyn Node<Output = f32>>>
+) -> Rc<dyn Node<Output = f32>> {
+    if let Some(n) = done.get(&node.id()) {
+        return n.clone();
+    }
+    let children = node.children();
+    let res = if children.is_empty() {
+        node.clone()
+    } else {
+        let cloned: Vec<_> = children.iter().map(|c| clone_node(c, done)).collect();
+        rebuild(&node.label(), &cloned).unwrap_or_else(|| node.clone())
+    };
+    done.insert(node.id(), res.clone());
+    res
+}
+
+/// Returns the deep copy of the nodes reachable from `root`, e.g. to inspect one output of a multi-output graph
+/// apart from the others. The shared nodes of the subgraph stay shared in the copy. The inputs and the constants
+/// are not copied, so the copy follows the changes of the inputs. Only the built-in operations (`add`, `sub`,
+/// `mul`, `div`, `pow`, `sin`) are copied, the other operations are shared with the original graph.
+pub fn subgraph_of(root: &Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    clone_node(root, &mut HashMap::new())
+}
+
+type Compiled = Rc<dyn Fn() -> f32>;
+
+fn compile_node(node: &Rc<dyn Node<Output = f32>>, done: &mut HashMap<usize, Compiled>) -> Compiled {
+    if let Some(f) = done.get(&node.id()) {
+        return f.clone();
+    }
+    let c: Vec<Compiled> = node.children().iter().map(|c| compile_node(c, done)).collect();
+    let f: Compiled = match (node.label().as_str(), c.as_slice(), node.constant_value()) {
+        (_, _, Some(v)) => Rc::new(move || v),
+        ("add", [x, y], _) => { let (x, y) = (x.clone(), y.clone()); Rc::new(move || x() + y()) }
+        ("sub", [x, y], _) => { let (x, y) = (x.clone(), y.clone()); Rc::new(move || x() - y()) }
+        ("mul", [x, y], _) => { let (x, y) = (x.clone(), y.clone()); Rc::new(move || x() * y()) }
+        ("div", [x, y], _) => { let (x, y) = (x.clone(), y.clone()); Rc::new(move || x() / y()) }
+        ("pow", [x, y], _) => { let (x, y) = (x.clone(), y.clone()); Rc::new(move || x().powf(y())) }
+        ("sin", [x], _) => { let x = x.clone(); Rc::new(move || x().sin()) }
+        // The inputs and the other operations are read through their nodes.
+        _ => { let node = node.clone(); Rc::new(move || node.compute()) }
+    };
+    done.insert(node.id(), f.clone());
+    f
+}
+
+/// Returns a closure computing the value of `root` directly from the current values of the inputs,
+/// without the dispatch and the caching of the built-in operations (`add`, `sub`, `mul`, `div`, `pow`, `sin`).
+/// The closure recomputes the whole graph on every call, and the structure of the graph is fixed at the compilation.
+/// The other operations are computed through their nodes.
+pub fn compile_closure(root: &Rc<dyn Node<Output = f32>>) -> Box<dyn Fn() -> f32> {
+    let f = compile_node(root, &mut HashMap::new());
+    Box::new(move || f())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::readme_graph;
+    use crate::*;
+    use crate::analysis::inputs_of;
+
+    #[test]
+    fn test_simplify_identity() {
+        let x = create_input("x");
+        let graph = add(mul(x.clone(), constant(1.0)), constant(0.0));
+        let simplified = simplify(&graph);
+        assert_eq!(simplified.id(), x.id());
+    }
+
+    #[test]
+    fn test_simplify_nested() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let graph = mul(
+            sin(pow(add(x1.clone(), constant(0.0)), constant(1.0))),
+            add(x2.clone(), mul(sub(x1.clone(), x1.clone()), x2.clone())),
+        );
+        let simplified = simplify(&graph);
+        x1.set(0.5);
+        x2.set(2.0);
+        assert_eq!(simplified.compute(), graph.compute());
+        assert_eq!(simplified.label(), "mul");
+        let children = simplified.children();
+        assert_eq!(children[0].children()[0].id(), x1.id());
+        assert_eq!(children[1].id(), x2.id());
+    }
+
+    #[test]
+    fn test_simplify_custom_sub() {
+        let x = create_input("x");
+        let graph = add(operations::Unary::named("sub", x.clone(), |x: f32| -x), constant(0.0));
+        let simplified = simplify(&graph);
+        assert_eq!(simplified.label(), "sub");
+        x.set(2.0);
+        assert_eq!(simplified.compute(), -2.0);
+    }
+
+    #[test]
+    fn test_subgraph_of() {
+        let (x1, x2, x3) = (create_input("x1"), create_input("x2"), create_input("x3"));
+        let shared = sin(x1.clone());
+        let first = add(add(shared.clone(), shared.clone()), x2.clone());
+        let _second = mul(x3.clone(), x2.clone());
+        let sub = subgraph_of(&first);
+        assert_ne!(sub.id(), first.id());
+        let labels: Vec<String> = inputs_of(&sub).iter().map(|n| n.label()).collect();
+        assert_eq!(labels, vec!["x1", "x2"]);
+        let copied = sub.children()[0].children();
+        assert!(Rc::ptr_eq(&copied[0], &copied[1]));
+        assert_ne!(copied[0].id(), shared.id());
+        x1.set(0.5);
+        x2.set(2.0);
+        assert_eq!(sub.compute(), first.compute());
+    }
+
+    #[test]
+    fn test_compile_closure() {
+        let (graph, [_, x2, _]) = readme_graph();
+        let f = compile_closure(&graph);
+        assert_eq!(f(), graph.compute());
+        x2.set(3.0);
+        assert_eq!(f(), graph.compute());
+        assert_eq!(graph.recompute_count(), 2);
+    }
+}
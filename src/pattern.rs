@@ -0,0 +1,87 @@
+//! Structural pattern matching of the computational graph, e.g. to find the subgraphs for rewriting.
+
+use crate::analysis::nodes_of;
+use crate::node::Node;
+
+use std::rc::Rc;
+
+/// Pattern describes a tree of operations with wildcards, e.g. `mul(_, 1.0)` is
+/// `Pattern::Op("mul", vec![Pattern::Any, Pattern::Const(1.0)])`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// Matches any node and binds it.
+    Any,
+    /// Matches a constant node with a given value.
+    Const(f32),
+    /// Matches a node with a given label whose inputs match the given patterns.
+    Op(&'static str, Vec<Pattern>)
+}
+
+/// Subgraph matched by a pattern.
+pub struct Match {
+    /// Root node of the matched subgraph.
+    pub node: Rc<dyn Node<Output = f32>>,
+    /// Nodes matched by the wildcards of the pattern in the pre-order of the pattern.
+    pub bindings: Vec<Rc<dyn Node<Output = f32>>>
+}
+
+impl Pattern {
+    /// Checks whether `node` matches the pattern and appends the nodes matched by the wildcards to `bindings`.
+    fn matches(&self, node: &Rc<dyn Node<Output = f32>>, bindings: &mut Vec<Rc<dyn Node<Output = f32>>>) -> bool {
+        match self {
+            Pattern::Any => {
+                bindings.push(node.clone());
+                true
+            }
+            Pattern::Const(v) => node.constant_value() == Some(*v),
+            Pattern::Op(label, args) => {
+                let children = node.children();
+                node.label() == *label
+                    && children.len() == args.len()
+                    && args.iter().zip(children.iter()).all(|(p, c)| p.matches(c, bindings))
+            }
+        }
+    }
+}
+
+impl Pattern {
+    /// Matches a single `node` against the pattern.
+    pub fn match_node(&self, node: &Rc<dyn Node<Output = f32>>) -> Option<Match> {
+        let mut bindings = Vec::new();
+        if self.matches(node, &mut bindings) {
+            Some(Match { node: node.clone(), bindings })
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns all the subgraphs reachable from `root` (including itself) that match the `pattern`,
+/// in the pre-order of the graph. Shared subgraphs are reported once.
+pub fn match_pattern(root: &Rc<dyn Node<Output = f32>>, pattern: &Pattern) -> Vec<Match> {
+    nodes_of(root).iter()
+        .filter_map(|node| pattern.match_node(node))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_match_add_zero() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let a = add(x1.clone(), constant(0.0));
+        let b = add(sin(x2.clone()), constant(0.0));
+        let graph = mul(a.clone(), add(b.clone(), constant(1.0)));
+        let pattern = Pattern::Op("add", vec![Pattern::Any, Pattern::Const(0.0)]);
+        let matches = match_pattern(&graph, &pattern);
+        assert_eq!(matches.len(), 2);
+        assert!(Rc::ptr_eq(&matches[0].node, &a));
+        assert_eq!(matches[0].bindings[0].id(), x1.id());
+        assert!(Rc::ptr_eq(&matches[1].node, &b));
+        assert_eq!(matches[1].bindings[0].label(), "sin");
+    }
+}
@@ -0,0 +1,85 @@
+//! Fluent construction of the computational graph as a chain of operations.
+
+use crate::node::Node;
+use crate::utils;
+
+use std::rc::Rc;
+
+/// Pipeline wraps the current node of the graph and applies the operations to it one after another,
+/// e.g. `Pipeline::new(x1).sin().mul(x2).add(x3).build()` builds `sin(x1) * x2 + x3`.
+#[derive(Clone)]
+pub struct Pipeline {
+    node: Rc<dyn Node<Output = f32>>
+}
+
+// Method names follow the helpers of `utils` rather than the operator traits, since they take nodes by value.
+#[allow(clippy::should_implement_trait)]
+impl Pipeline {
+    /// Start the pipeline from a given node.
+    pub fn new(node: Rc<dyn Node<Output = f32>>) -> Self {
+        Pipeline { node }
+    }
+
+    /// Apply an arbitrary operation to the current node.
+    pub fn then(self, f: impl FnOnce(Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>>) -> Self {
+        Pipeline { node: f(self.node) }
+    }
+
+    /// Add the value of `other` node to the current node.
+    pub fn add(self, other: Rc<dyn Node<Output = f32>>) -> Self {
+        self.then(|x| utils::add(x, other))
+    }
+
+    /// Subtract the value of `other` node from the current node.
+    pub fn sub(self, other: Rc<dyn Node<Output = f32>>) -> Self {
+        self.then(|x| utils::sub(x, other))
+    }
+
+    /// Multiply the current node by the value of `other` node.
+    pub fn mul(self, other: Rc<dyn Node<Output = f32>>) -> Self {
+        self.then(|x| utils::mul(x, other))
+    }
+
+    /// Apply trigonometric sinus to the current node.
+    pub fn sin(self) -> Self {
+        self.then(utils::sin)
+    }
+
+    /// Raise the current node to the power `e`.
+    pub fn pow_f32(self, e: f32) -> Self {
+        self.then(|x| utils::pow_f32(x, e))
+    }
+
+    /// Raise the current node to the integer power `n`.
+    pub fn powi(self, n: i32) -> Self {
+        self.then(|x| utils::powi(x, n))
+    }
+
+    /// Returns the final node of the pipeline.
+    pub fn build(self) -> Rc<dyn Node<Output = f32>> {
+        self.node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_input;
+
+    #[test]
+    fn test_pipeline() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let x3 = create_input("x3");
+        let graph = Pipeline::new(x1.clone()).sin().mul(x2.clone()).add(x3.clone()).build();
+        x1.set(1.0);
+        x2.set(2.0);
+        x3.set(3.0);
+        assert_eq!(graph.compute(), 1f32.sin() * 2.0 + 3.0);
+        assert_eq!(graph.compute(), 1f32.sin() * 2.0 + 3.0);
+        assert_eq!(graph.recompute_count(), 1);
+        x3.set(-1.0);
+        assert_eq!(graph.compute(), 1f32.sin() * 2.0 - 1.0);
+        assert_eq!(graph.children()[0].recompute_count(), 1);
+    }
+}
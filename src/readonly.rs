@@ -0,0 +1,52 @@
+//! Read-only view of the computational graph, e.g. to pass it to untrusted code.
+//!
+//! The view only computes the value of the graph, it can not change the inputs, invalidate
+//! the caches or add the dependent nodes:
+//! ```compile_fail
+//! # use computational_graph::*;
+//! let x = create_input("x");
+//! let graph = ReadOnlyGraph::new(sin(x.clone()));
+//! graph.invalidate();
+//! ```
+//! ```compile_fail
+//! # use computational_graph::*;
+//! let x = create_input::<f32>("x");
+//! let graph = ReadOnlyGraph::new(x.clone());
+//! graph.set(1.0);
+//! ```
+
+use crate::node::Node;
+
+use std::rc::Rc;
+
+/// Wrapper of the root node of the graph, that forwards only `compute`.
+pub struct ReadOnlyGraph<T> {
+    root: Rc<dyn Node<Output = T>>
+}
+
+impl<T> ReadOnlyGraph<T> {
+    pub fn new(root: Rc<dyn Node<Output = T>>) -> Self {
+        ReadOnlyGraph { root }
+    }
+
+    /// Returns the value of the root node of the graph.
+    pub fn compute(&self) -> T {
+        self.root.compute()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_read_only() {
+        let x = create_input("x");
+        let graph = ReadOnlyGraph::new(mul(x.clone(), x.clone()));
+        x.set(3.0);
+        assert_eq!(graph.compute(), 9.0);
+        x.set(-2.0);
+        assert_eq!(graph.compute(), 4.0);
+    }
+}
@@ -0,0 +1,142 @@
+//! Recording of the changes of the input values and their replay through the graph.
+
+use crate::node::Node;
+use crate::operations::Input;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Recorder appends `(timestamp, value)` of every change of the value of the tracked inputs to its log.
+/// The `set` calls with the current value are not recorded, and the `set` of the input following another input
+/// is recorded by the followed input, if it is tracked.
+/// Inputs are tracked by opt-in with [`Recorder::track`].
+#[derive(Default)]
+pub struct Recorder<'a, T: Copy = f32> {
+    inputs: RefCell<Vec<Rc<Input<'a, T>>>>,
+    log: RefCell<Vec<(Instant, usize, T)>>,
+    replaying: Cell<bool>
+}
+
+impl<'a, T: Copy + Default> Recorder<'a, T> {
+    pub fn new() -> Rc<Self> {
+        Default::default()
+    }
+}
+
+impl<'a, T: Copy> Recorder<'a, T> {
+    /// Start recording the changes of a given input. The input records to a single recorder: tracking it
+    /// replaces the recorder the input was tracked by before, that does not record its changes any more.
+    pub fn track(self: &Rc<Self>, input: &Rc<Input<'a, T>>) {
+        let mut inputs = self.inputs.borrow_mut();
+        input.record_to(Rc::downgrade(self), inputs.len());
+        inputs.push(input.clone());
+    }
+
+    /// Append new value `x` of the tracked input with index `i` to the log.
+    pub(crate) fn record(&self, i: usize, x: T) {
+        if !self.replaying.get() {
+            self.log.borrow_mut().push((Instant::now(), i, x));
+        }
+    }
+
+    /// Returns the recorded changes as `(timestamp, input name, value)`.
+    pub fn entries(&self) -> Vec<(Instant, String, T)> {
+        let inputs = self.inputs.borrow();
+        self.log.borrow().iter()
+            .map(|&(t, i, x)| (t, inputs[i].label(), x))
+            .collect()
+    }
+}
+
+/// Replay mode of the recorder, that ends on drop.
+struct Replaying<'r>(&'r Cell<bool>);
+
+impl<'r> Replaying<'r> {
+    fn enter(replaying: &'r Cell<bool>) -> Self {
+        replaying.set(true);
+        Self(replaying)
+    }
+}
+
+impl Drop for Replaying<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Re-applies the recorded changes of the inputs in their order and returns the value of `root` after each change.
+/// The replayed changes are not recorded again, the recording resumes after the replay, also if it panics.
+pub fn replay<T: Copy + PartialEq>(root: &Rc<dyn Node<Output = T>>, recorder: &Recorder<T>) -> Vec<T> {
+    let log = recorder.log.borrow().clone();
+    let inputs = recorder.inputs.borrow().clone();
+    let _replaying = Replaying::enter(&recorder.replaying);
+    log.iter()
+        .map(|&(_, i, x)| {
+            inputs[i].set(x);
+            root.compute()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use crate::operations::Unary;
+
+    #[test]
+    fn test_replay() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let graph = mul(x1.clone(), x2.clone());
+        let recorder = Recorder::new();
+        recorder.track(&x1);
+        x2.set(2.0);
+        let mut outputs = Vec::new();
+        for v in [1.0, 3.0, -2.0] {
+            x1.set(v);
+            outputs.push(graph.compute());
+        }
+        let entries = recorder.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].1, "x1");
+        assert_eq!(entries[1].2, 3.0);
+        assert_eq!(replay(&graph, &recorder), outputs);
+        assert_eq!(recorder.entries().len(), 3);
+    }
+
+    #[test]
+    fn test_replay_panic() {
+        let x = create_input("x");
+        let graph: Rc<dyn Node<Output = f32>> = Unary::named("checked", x.clone(), |x: f32| {
+            assert!(x >= 0.0, "negative value");
+            x
+        });
+        let recorder = Recorder::new();
+        recorder.track(&x);
+        x.set(-1.0);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| replay(&graph, &recorder)));
+        assert!(res.is_err());
+        x.set(2.0);
+        assert_eq!(recorder.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_record_changes_only() {
+        let ctx = GraphContext::new();
+        let x1 = ctx.input("x1");
+        let other = GraphContext::new();
+        let follower = other.input("x1");
+        ctx.merge(other);
+        let recorder = Recorder::new();
+        recorder.track(&x1);
+        recorder.track(&follower);
+        x1.set(1.0);
+        x1.set(1.0);
+        follower.set(2.0);
+        let entries: Vec<(String, f32)> = recorder.entries().into_iter().map(|(_, name, x)| (name, x)).collect();
+        assert_eq!(entries, vec![(String::from("x1"), 1.0), (String::from("x1"), 2.0)]);
+        assert_eq!(recorder.log.borrow().iter().map(|&(_, i, _)| i).collect::<Vec<_>>(), vec![0, 0]);
+    }
+}
@@ -0,0 +1,154 @@
+//! Scalar trait abstracting the value types of the numeric graphs, e.g. `f32`, `f64` or fixed-point numbers,
+//! and the helpers generic over it.
+//!
+//! The arithmetic helpers [`crate::add`], [`crate::sub`] and [`crate::mul`] already work for any type
+//! with the corresponding operators, this module adds the operations that are not expressed with operators.
+
+use crate::node::Node;
+use crate::operations::{Constant, Unary};
+use crate::utils::generic_binary;
+
+use std::ops::{Add, Mul};
+use std::rc::Rc;
+
+/// Operations on the values of the numeric graphs.
+pub trait Scalar: Copy + Default + PartialEq + ToString + Add<Output = Self> + Mul<Output = Self> + 'static {
+    /// Returns the additive identity.
+    fn zero() -> Self;
+    /// Returns the multiplicative identity.
+    fn one() -> Self;
+    /// Returns the trigonometric sinus of the value.
+    fn sin(self) -> Self;
+    /// Returns the value raised to the power `e`.
+    fn powf(self, e: Self) -> Self;
+}
+
+impl Scalar for f32 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn sin(self) -> Self {
+        f32::sin(self)
+    }
+
+    fn powf(self, e: Self) -> Self {
+        f32::powf(self, e)
+    }
+}
+
+impl Scalar for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+
+    fn powf(self, e: Self) -> Self {
+        f64::powf(self, e)
+    }
+}
+
+/// Creates constant node of the additive identity.
+pub fn zero<S: Scalar>() -> Rc<dyn Node<Output = S>> {
+    Rc::new(Constant::new(S::zero()))
+}
+
+/// Creates constant node of the multiplicative identity.
+pub fn one<S: Scalar>() -> Rc<dyn Node<Output = S>> {
+    Rc::new(Constant::new(S::one()))
+}
+
+/// Creates node that compute trigonometric sinus of a value of a given node and cache it.
+pub fn sin<S: Scalar>(x: Rc<dyn Node<Output = S>>) -> Rc<dyn Node<Output = S>> {
+    Unary::named("sin", x, S::sin)
+}
+
+/// Creates node that raise the value of some node to the power given by the value of another node.
+pub fn pow<S: Scalar>(x: Rc<dyn Node<Output = S>>, e: Rc<dyn Node<Output = S>>) -> Rc<dyn Node<Output = S>> {
+    generic_binary("pow", x, e, S::powf, |x, e| [e * x.powf(e - 1.0), x.powf(e) * x.ln()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{add, mul, create_input};
+
+    use std::fmt;
+
+    /// Fixed-point number with 16 fractional bits.
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct Fixed(i32);
+
+    impl Fixed {
+        fn from_f64(x: f64) -> Self {
+            Fixed((x * 65536.0).round() as i32)
+        }
+
+        fn to_f64(self) -> f64 {
+            self.0 as f64 / 65536.0
+        }
+    }
+
+    impl fmt::Display for Fixed {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.to_f64())
+        }
+    }
+
+    impl Add for Fixed {
+        type Output = Fixed;
+
+        fn add(self, rhs: Fixed) -> Fixed {
+            Fixed(self.0 + rhs.0)
+        }
+    }
+
+    impl Mul for Fixed {
+        type Output = Fixed;
+
+        fn mul(self, rhs: Fixed) -> Fixed {
+            Fixed(((self.0 as i64 * rhs.0 as i64) >> 16) as i32)
+        }
+    }
+
+    impl Scalar for Fixed {
+        fn zero() -> Self {
+            Fixed(0)
+        }
+
+        fn one() -> Self {
+            Fixed(1 << 16)
+        }
+
+        fn sin(self) -> Self {
+            Fixed::from_f64(self.to_f64().sin())
+        }
+
+        fn powf(self, e: Self) -> Self {
+            Fixed::from_f64(self.to_f64().powf(e.to_f64()))
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_graph() {
+        let x = create_input::<Fixed>("x");
+        let y = create_input::<Fixed>("y");
+        let graph = add(mul(x.clone(), y.clone()), pow(one(), sin(x.clone())));
+        x.set(Fixed::from_f64(1.5));
+        y.set(Fixed::from_f64(-2.25));
+        assert_eq!(graph.compute(), Fixed::from_f64(-3.375 + 1.0));
+        y.set(Fixed::zero());
+        assert_eq!(graph.compute(), Fixed::one());
+    }
+}
@@ -0,0 +1,67 @@
+//! Saving and restoring the internal state of the stateful nodes of the computational graph.
+//!
+//! The structure of the graph is not stored, only the states of the stateful nodes keyed by the traversal index
+//! of the node, that is its position in [`nodes_of`] of the root (not [`Node::id`]).
+//! Thus the state can be loaded into the graph rebuilt with the same structure.
+//!
+//! The states are kept in memory as the copies of the state values of the nodes, [`StateMap`] is not
+//! a serialized form. To persist it, downcast the states to the state types of the known nodes.
+
+use crate::analysis::nodes_of;
+use crate::node::Node;
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// In-memory states of the stateful nodes keyed by the traversal index of the node.
+pub type StateMap = HashMap<usize, Box<dyn Any>>;
+
+/// Returns the states of all the stateful nodes reachable from `root`.
+pub fn dump_state<T>(root: &Rc<dyn Node<Output = T>>) -> StateMap {
+    nodes_of(root).iter()
+        .enumerate()
+        .filter_map(|(i, n)| n.save_state().map(|s| (i, s)))
+        .collect()
+}
+
+/// Restores the states of the stateful nodes reachable from `root` from the `states`.
+pub fn load_state<T>(root: &Rc<dyn Node<Output = T>>, states: &StateMap) {
+    for (i, n) in nodes_of(root).iter().enumerate() {
+        if let Some(s) = states.get(&i) {
+            n.load_state(s.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_input;
+    use crate::operations::Stateful;
+
+    fn accumulate(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+        Stateful::new(vec![x], 0.0, |s: &mut f32, xs: &[f32]| { *s += xs[0]; *s })
+    }
+
+    #[test]
+    fn test_state_restore() {
+        let x = create_input("x");
+        let graph = accumulate(x.clone());
+        for v in [1.0, 2.0, 3.0] {
+            x.set(v);
+            graph.compute();
+        }
+        assert_eq!(graph.compute(), 6.0);
+        let states = dump_state(&graph);
+        assert_eq!(states.len(), 1);
+        // the state value of the accumulator, e.g. to persist it
+        assert_eq!(states[&0].downcast_ref::<f32>(), Some(&6.0));
+
+        let x = create_input("x");
+        let graph = accumulate(x.clone());
+        load_state(&graph, &states);
+        x.set(4.0);
+        assert_eq!(graph.compute(), 10.0);
+    }
+}
@@ -0,0 +1,310 @@
+//! Thread-safe variant of the computational graph, built on `Arc` and locks instead of `Rc` and `RefCell`.
+//! The nodes are `Send + Sync`, so the inputs can be shared by the threads and the outputs computed in parallel.
+//!
+//! The cache of the node is locked for the time of the computation, so the concurrent requests of the same
+//! node run the operation once and get the same stored value. The input stores the new value before
+//! the invalidation of the dependent nodes, so no stale value is left in the caches after `set` returns.
+
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// Node trait of the thread-safe graph, having the same shape as [`crate::Node`].
+pub trait Node: Send + Sync {
+    type Output;
+
+    /// Provides the value of the node, from the cache or by the computation.
+    fn compute(&self) -> Self::Output;
+    /// Invalidate the cache of the current node and the dependent nodes.
+    fn invalidate(&self);
+    /// Add some node n to the list of the nodes that are dependent of the value of this node.
+    fn add_dependent(&self, n: Arc<dyn Dependent>);
+    /// Returns the label of the node: the name of the operation or the name of the input.
+    fn label(&self) -> String {
+        String::from("node")
+    }
+}
+
+/// Part of the node interface that does not depend on the output type of the node.
+pub trait Dependent: Send + Sync {
+    /// Invalidate the cache of the dependent node and its own dependent nodes.
+    fn invalidate_dependent(&self);
+}
+
+impl<N: Node + ?Sized> Dependent for N {
+    fn invalidate_dependent(&self) {
+        self.invalidate();
+    }
+}
+
+/// Links to the dependent nodes.
+#[derive(Default)]
+struct Dependencies {
+    vec: RwLock<Vec<Weak<dyn Dependent>>>
+}
+
+impl Dependencies {
+    fn add(&self, n: Arc<dyn Dependent>) {
+        let mut vec = self.vec.write().unwrap();
+        if vec.len() == vec.capacity() {
+            vec.retain(|d| d.strong_count() > 0);
+        }
+        vec.push(Arc::downgrade(&n));
+    }
+
+    fn invalidate(&self) {
+        // The live nodes are collected first, so the lock is not held while they are invalidated.
+        let live: Vec<_> = self.vec.read().unwrap().iter().filter_map(Weak::upgrade).collect();
+        for x in live {
+            x.invalidate_dependent();
+        }
+    }
+}
+
+/// Cache of the value guarded by a mutex.
+struct Cache<T> {
+    val: Mutex<Option<T>>
+}
+
+impl<T: Copy> Cache<T> {
+    fn new() -> Self {
+        Cache { val: Mutex::new(None) }
+    }
+
+    /// Returns the stored value or computes it with `f` and stores it. The lock is held during the computation.
+    fn get_or_else(&self, f: impl Fn() -> T) -> T {
+        let mut val = self.val.lock().unwrap();
+        *val.get_or_insert_with(f)
+    }
+
+    fn invalidate(&self) {
+        self.val.lock().unwrap().take();
+    }
+}
+
+/// Input node present some input value of type `T` (`f32` by default).
+pub struct Input<'a, T = f32> {
+    name: &'a str,
+    value: RwLock<T>,
+    dep: Dependencies
+}
+
+impl<'a, T: Copy + Default + PartialEq + Send + Sync> Input<'a, T> {
+    pub fn new(name: &'a str) -> Self {
+        Input { name, value: RwLock::new(T::default()), dep: Default::default() }
+    }
+
+    /// Set new value `x` and invalidate the caches of the dependent nodes. Setting the same value is skipped.
+    pub fn set(&self, x: T) {
+        {
+            let mut value = self.value.write().unwrap();
+            if *value == x {
+                return;
+            }
+            *value = x;
+        }
+        self.invalidate();
+    }
+}
+
+impl<'a, T: Copy + Send + Sync> Node for Input<'a, T> {
+    type Output = T;
+
+    fn compute(&self) -> T {
+        *self.value.read().unwrap()
+    }
+
+    fn invalidate(&self) {
+        self.dep.invalidate();
+    }
+
+    fn add_dependent(&self, n: Arc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+/// Unary node applying operation `op` to the value of `x` and caching the result.
+pub struct Unary<F, T = f32> {
+    label: &'static str,
+    x: Arc<dyn Node<Output = T>>,
+    op: F,
+    cached: Cache<T>,
+    dep: Dependencies
+}
+
+impl<F: Fn(T) -> T + Send + Sync + 'static, T: Copy + Send + Sync + 'static> Unary<F, T> {
+    /// Create new unary node with a given label of the operation.
+    pub fn named(label: &'static str, x: Arc<dyn Node<Output = T>>, op: F) -> Arc<Self> {
+        let tmp = Arc::new(Self { label, x: x.clone(), op, cached: Cache::new(), dep: Default::default() });
+        x.add_dependent(tmp.clone());
+        tmp
+    }
+}
+
+impl<F: Fn(T) -> T + Send + Sync, T: Copy + Send + Sync> Node for Unary<F, T> {
+    type Output = T;
+
+    fn compute(&self) -> T {
+        self.cached.get_or_else(|| (self.op)(self.x.compute()))
+    }
+
+    fn invalidate(&self) {
+        self.cached.invalidate();
+        self.dep.invalidate();
+    }
+
+    fn add_dependent(&self, n: Arc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+}
+
+/// Binary node applying operation `op` to the values of `x` and `y` and caching the result.
+pub struct Binary<F, T = f32> {
+    label: &'static str,
+    x: Arc<dyn Node<Output = T>>,
+    y: Arc<dyn Node<Output = T>>,
+    op: F,
+    cached: Cache<T>,
+    dep: Dependencies
+}
+
+impl<F: Fn(T, T) -> T + Send + Sync + 'static, T: Copy + Send + Sync + 'static> Binary<F, T> {
+    /// Create new binary node with a given label of the operation.
+    pub fn named(label: &'static str, x: Arc<dyn Node<Output = T>>, y: Arc<dyn Node<Output = T>>, op: F) -> Arc<Self> {
+        let tmp = Arc::new(
+            Self { label, x: x.clone(), y: y.clone(), op, cached: Cache::new(), dep: Default::default() }
+        );
+        x.add_dependent(tmp.clone());
+        y.add_dependent(tmp.clone());
+        tmp
+    }
+}
+
+impl<F: Fn(T, T) -> T + Send + Sync, T: Copy + Send + Sync> Node for Binary<F, T> {
+    type Output = T;
+
+    fn compute(&self) -> T {
+        self.cached.get_or_else(|| (self.op)(self.x.compute(), self.y.compute()))
+    }
+
+    fn invalidate(&self) {
+        self.cached.invalidate();
+        self.dep.invalidate();
+    }
+
+    fn add_dependent(&self, n: Arc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+}
+
+/// Creates input node of the thread-safe graph with a given name.
+pub fn create_input<T: Copy + Default + PartialEq + Send + Sync>(name: &str) -> Arc<Input<'_, T>> {
+    Arc::new(Input::new(name))
+}
+
+/// Creates summation node of the thread-safe graph.
+pub fn add<T>(x: Arc<dyn Node<Output = T>>, y: Arc<dyn Node<Output = T>>) -> Arc<dyn Node<Output = T>>
+where T: Copy + Send + Sync + std::ops::Add<Output = T> + 'static {
+    Binary::named("add", x, y, |x, y| x + y)
+}
+
+/// Creates subtraction node of the thread-safe graph.
+pub fn sub<T>(x: Arc<dyn Node<Output = T>>, y: Arc<dyn Node<Output = T>>) -> Arc<dyn Node<Output = T>>
+where T: Copy + Send + Sync + std::ops::Sub<Output = T> + 'static {
+    Binary::named("sub", x, y, |x, y| x - y)
+}
+
+/// Creates multiplication node of the thread-safe graph.
+pub fn mul<T>(x: Arc<dyn Node<Output = T>>, y: Arc<dyn Node<Output = T>>) -> Arc<dyn Node<Output = T>>
+where T: Copy + Send + Sync + std::ops::Mul<Output = T> + 'static {
+    Binary::named("mul", x, y, |x, y| x * y)
+}
+
+/// Creates trigonometric sinus node of the thread-safe graph.
+pub fn sin(x: Arc<dyn Node<Output = f32>>) -> Arc<dyn Node<Output = f32>> {
+    Unary::named("sin", x, f32::sin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_parallel_outputs() {
+        let x = create_input::<f32>("x");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let shared: Arc<dyn Node<Output = f32>> = {
+            let calls = calls.clone();
+            Unary::named("counted_sin", x.clone(), move |x: f32| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                x.sin()
+            })
+        };
+        let outputs: Vec<Arc<dyn Node<Output = f32>>> = (1..=4)
+            .map(|i| {
+                let c = create_input::<f32>("c");
+                c.set(i as f32);
+                mul(add(shared.clone(), x.clone()), c)
+            })
+            .collect();
+        x.set(0.5);
+        let expected: Vec<f32> = outputs.iter().map(|n| n.compute()).collect();
+        x.set(2.0);
+        x.set(0.5);
+        let handles: Vec<_> = outputs.iter().cloned().map(|n| thread::spawn(move || n.compute())).collect();
+        let results: Vec<f32> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results, expected);
+        // once for the single-threaded run and once for all the threads
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_concurrent_set() {
+        let x = create_input::<f32>("x");
+        let graph = mul(x.clone(), x.clone());
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let graph = graph.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let v = graph.compute();
+                        assert!(v >= 0.0);
+                    }
+                })
+            })
+            .collect();
+        for i in 0..1000 {
+            x.set(i as f32);
+        }
+        for r in readers {
+            r.join().unwrap();
+        }
+        assert_eq!(graph.compute(), 999.0 * 999.0);
+    }
+
+    #[test]
+    fn test_build_and_compute_on_other_threads() {
+        let (x, graph) = thread::spawn(|| {
+            let x = create_input::<f32>("x");
+            let y = create_input::<f32>("y");
+            y.set(0.5);
+            let graph = sub(mul(sin(x.clone()), y), x.clone());
+            (x, graph)
+        }).join().unwrap();
+        x.set(1.0);
+        let value = thread::spawn(move || graph.compute()).join().unwrap();
+        assert_eq!(value, 1f32.sin() * 0.5 - 1.0);
+    }
+}
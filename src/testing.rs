@@ -0,0 +1,99 @@
+//! Helpers for the tests of the graph transformations, enabled with `testing` feature.
+
+use crate::analysis::nodes_of;
+use crate::display::{explain, expression};
+use crate::node::Node;
+use crate::operations::Input;
+
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// Total number of the recomputations of the nodes of the graph.
+fn total_recomputes<T>(root: &Rc<dyn Node<Output = T>>) -> u64 {
+    nodes_of(root).iter().map(|n| n.recompute_count()).sum()
+}
+
+/// Runs `original` and `optimized` graphs on the same sequence of the input values and asserts that they produce
+/// identical outputs and the optimized graph recomputes no more nodes than the original one.
+/// Each input is given with the sequence of its values, on the step `i` all the inputs are set to their `i`-th values.
+pub fn assert_same_recompute_profile<T: Copy + PartialEq + Debug>(
+    original: &Rc<dyn Node<Output = T>>,
+    optimized: &Rc<dyn Node<Output = T>>,
+    inputs: &[(&Rc<Input<'_, T>>, Vec<T>)]
+) {
+    let steps = inputs.iter().map(|(_, values)| values.len()).min().unwrap_or(0);
+    let (before_original, before_optimized) = (total_recomputes(original), total_recomputes(optimized));
+    for i in 0..steps {
+        for (x, values) in inputs {
+            x.set(values[i]);
+        }
+        assert_eq!(original.compute(), optimized.compute(), "outputs differ on step {}", i);
+    }
+    let original_recomputes = total_recomputes(original) - before_original;
+    let optimized_recomputes = total_recomputes(optimized) - before_optimized;
+    assert!(
+        optimized_recomputes <= original_recomputes,
+        "optimized graph recomputes {} nodes, original graph {}", optimized_recomputes, original_recomputes
+    );
+}
+
+/// Asserts that the value of `node` differs from `expected` at most by `tolerance`. On failure the message
+/// gives the expression of the node and the step-by-step computation of the intermediate values.
+pub fn assert_computes(node: &Rc<dyn Node<Output = f32>>, expected: f32, tolerance: f32) {
+    let value = node.compute();
+    assert!(
+        (value - expected).abs() <= tolerance,
+        "{} = {}, expected {} within {}\nsteps: {}", expression(node), value, expected, tolerance, explain(node)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::readme_graph;
+    use crate::operations::Unary;
+    use crate::optimize::simplify;
+    use crate::*;
+
+    #[test]
+    fn test_simplify_profile() {
+        let x = create_input("x");
+        let y = create_input("y");
+        let graph = add(mul(sin(x.clone()), constant(1.0)), sub(y.clone(), constant(0.0)));
+        let simplified = simplify(&graph);
+        let sweep: Vec<f32> = (0..20).map(|i| i as f32 * 0.25).collect();
+        let reversed: Vec<f32> = sweep.iter().rev().cloned().collect();
+        assert_same_recompute_profile(&graph, &simplified, &[(&x, sweep), (&y, reversed)]);
+    }
+
+    #[test]
+    fn test_typed_inputs_profile() {
+        let x = create_input::<f64>("x");
+        let graph: Rc<dyn Node<Output = f64>> = Unary::new(x.clone(), |x: f64| x * 2.0);
+        let other: Rc<dyn Node<Output = f64>> = Unary::new(x.clone(), |x: f64| x + x);
+        assert_same_recompute_profile(&graph, &other, &[(&x, vec![0.5, 1e300, -3.0])]);
+    }
+
+    #[test]
+    #[should_panic(expected = "outputs differ")]
+    fn test_different_outputs() {
+        let x = create_input("x");
+        let graph = sin(x.clone());
+        let other = mul(x.clone(), constant(1.0));
+        assert_same_recompute_profile(&graph, &other, &[(&x, vec![0.0, 1.0])]);
+    }
+
+
+    #[test]
+    fn test_assert_computes() {
+        let (graph, _) = readme_graph();
+        assert_computes(&graph, -0.32727, 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "x1 + (x2 * sin(x2 + (x3 ^ 3)))")]
+    fn test_assert_computes_diagnostic() {
+        let (graph, _) = readme_graph();
+        assert_computes(&graph, 1.0, 1e-5);
+    }
+}
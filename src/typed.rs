@@ -0,0 +1,212 @@
+//! Typed handles over the graph nodes, that distinguish scalar and vector values at compile time.
+//!
+//! The operations on handles accept only the values of the proper kind, so the shape mismatch
+//! is a compile error rather than a runtime panic:
+//! ```compile_fail
+//! # use computational_graph::typed::*;
+//! let x = Scalar::from(computational_graph::create_input("x"));
+//! // dot product is defined only for the vectors
+//! let y = dot(&x, &x);
+//! ```
+
+use crate::node::{Node, Dependent, Dependencies, next_generation};
+use crate::cache::{Cache, cached_node_methods};
+use crate::utils;
+
+use std::cell::Cell;
+use std::rc::{Rc, Weak};
+
+/// Handle to a node producing a scalar value.
+#[derive(Clone)]
+pub struct Scalar(Rc<dyn Node<Output = f32>>);
+
+/// Handle to a node producing a vector of `N` values.
+#[derive(Clone)]
+pub struct VectorN<const N: usize>(Rc<dyn Node<Output = [f32; N]>>);
+
+impl Scalar {
+    /// Returns the value of the underlying node.
+    pub fn compute(&self) -> f32 {
+        self.0.compute()
+    }
+
+    /// Returns the underlying node.
+    pub fn node(&self) -> Rc<dyn Node<Output = f32>> {
+        self.0.clone()
+    }
+}
+
+impl<T: Node<Output = f32> + 'static> From<Rc<T>> for Scalar {
+    fn from(x: Rc<T>) -> Self {
+        Scalar(x)
+    }
+}
+
+impl From<Rc<dyn Node<Output = f32>>> for Scalar {
+    fn from(x: Rc<dyn Node<Output = f32>>) -> Self {
+        Scalar(x)
+    }
+}
+
+impl<const N: usize> VectorN<N> {
+    /// Returns the value of the underlying node.
+    pub fn compute(&self) -> [f32; N] {
+        self.0.compute()
+    }
+
+    /// Returns the underlying node.
+    pub fn node(&self) -> Rc<dyn Node<Output = [f32; N]>> {
+        self.0.clone()
+    }
+}
+
+impl<const N: usize, T: Node<Output = [f32; N]> + 'static> From<Rc<T>> for VectorN<N> {
+    fn from(x: Rc<T>) -> Self {
+        VectorN(x)
+    }
+}
+
+/// Input node present some vector input value of `N` elements.
+pub struct VectorInput<'a, const N: usize> {
+    name: &'a str,
+    value: Cell<[f32; N]>,
+    dep: Dependencies
+}
+
+impl<'a, const N: usize> VectorInput<'a, N> {
+    pub fn new(name: &'a str) -> Self {
+        VectorInput { name, value: Cell::new([0.0; N]), dep: Default::default() }
+    }
+
+    /// Set new value `x` and require invalidation of the caches of the dependent nodes.
+    /// Like [`Input::set`](crate::operations::Input::set), setting the value equal to the current one is skipped.
+    pub fn set(&self, x: [f32; N]) {
+        if self.value.get() == x {
+            return;
+        }
+        next_generation();
+        self.invalidate();
+        self.value.set(x);
+    }
+}
+
+impl<'a, const N: usize> Node for VectorInput<'a, N> {
+    type Output = [f32; N];
+
+    fn compute(&self) -> Self::Output {
+        self.value.get()
+    }
+
+    fn invalidate(&self) {
+        self.dep.invalidate();
+    }
+
+    fn add_dependent(&self, n: Rc<dyn Dependent>) {
+        self.dep.add(n);
+    }
+
+    fn take_dependents(&self) -> Vec<Weak<dyn Dependent>> {
+        self.dep.take()
+    }
+
+    fn restore_dependents(&self, deps: Vec<Weak<dyn Dependent>>) {
+        self.dep.restore(deps);
+    }
+
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn is_input(&self) -> bool {
+        true
+    }
+}
+
+/// Dot node computes the scalar product of two vector nodes and cache it.
+struct Dot<const N: usize> {
+    x: Rc<dyn Node<Output = [f32; N]>>,
+    y: Rc<dyn Node<Output = [f32; N]>>,
+    cached: Cache<f32>,
+    dep: Dependencies
+}
+
+impl<const N: usize> Node for Dot<N> {
+    type Output = f32;
+
+    fn compute(&self) -> f32 {
+        self.cached.get_or_else(|| {
+            let (x, y) = (self.x.compute(), self.y.compute());
+            x.iter().zip(y.iter()).map(|(a, b)| a * b).sum()
+        })
+    }
+
+    cached_node_methods!();
+
+    fn label(&self) -> String {
+        String::from("dot")
+    }
+}
+
+/// Creates vector input node of the compute graph with a given name.
+pub fn vector_input<const N: usize>(name: &str) -> Rc<VectorInput<'_, N>> {
+    Rc::new(VectorInput::new(name))
+}
+
+/// Creates summation node of two scalars.
+pub fn add(x: &Scalar, y: &Scalar) -> Scalar {
+    Scalar(utils::add(x.node(), y.node()))
+}
+
+/// Creates multiplication node of two scalars.
+pub fn mul(x: &Scalar, y: &Scalar) -> Scalar {
+    Scalar(utils::mul(x.node(), y.node()))
+}
+
+/// Creates trigonometric sinus node of a scalar.
+pub fn sin(x: &Scalar) -> Scalar {
+    Scalar(utils::sin(x.node()))
+}
+
+/// Creates node of the scalar product of two vectors of the same length.
+pub fn dot<const N: usize>(x: &VectorN<N>, y: &VectorN<N>) -> Scalar {
+    let tmp = Rc::new(Dot { x: x.node(), y: y.node(), cached: Cache::new(), dep: Default::default() });
+    x.0.add_dependent(tmp.clone());
+    y.0.add_dependent(tmp.clone());
+    Scalar(tmp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_input, generation};
+
+    #[test]
+    fn test_typed_graph() {
+        let v1 = vector_input::<3>("v1");
+        let v2 = vector_input::<3>("v2");
+        let x = create_input("x");
+        let graph = add(&Scalar::from(x.clone()), &dot(&VectorN::from(v1.clone()), &VectorN::from(v2.clone())));
+        v1.set([1.0, 2.0, 3.0]);
+        v2.set([4.0, 5.0, 6.0]);
+        x.set(1.0);
+        assert_eq!(graph.compute(), 33.0);
+        v2.set([1.0, 0.0, -1.0]);
+        assert_eq!(graph.compute(), -1.0);
+    }
+
+    #[test]
+    fn test_vector_input_set() {
+        let v = vector_input::<2>("v");
+        let graph = dot(&VectorN::from(v.clone()), &VectorN::from(v.clone()));
+        v.set([3.0, 4.0]);
+        let before = generation();
+        assert_eq!(graph.compute(), 25.0);
+        v.set([3.0, 4.0]);
+        assert_eq!(generation(), before);
+        assert_eq!(graph.compute(), 25.0);
+        assert_eq!(graph.node().recompute_count(), 1);
+        v.set([0.0, 1.0]);
+        assert_eq!(generation(), before + 1);
+        assert_eq!(graph.compute(), 1.0);
+    }
+}
@@ -1,31 +1,1745 @@
 //! Includes some functions to create computational graph with common math operations.
 
-use crate::node::Node;
-use crate::operations::{Input, Binary, Unary};
+use crate::node::{Node, Dependent, Dependencies, Partials, invalidation_pass};
+use crate::operations::{Input, SliceInput, Constant, Binary, Unary, Ternary, NAry, Stateful, Map};
 
-use std::rc::Rc;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ops::{Add, Div, Mul, Sub};
+use std::rc::{Rc, Weak};
+use std::time::Instant;
 
-/// Creates input node of the compute graph with a given name
-pub fn create_input(name: &str) -> Rc<Input>{
+/// Extension of the node handles returned by the helpers, that configures caching of the node.
+pub trait CachingExt {
+    /// Disable caching of the node value, so it is recomputed on every request.
+    fn without_cache(self) -> Self;
+}
+
+impl<T> CachingExt for Rc<dyn Node<Output = T>> {
+    fn without_cache(self) -> Self {
+        self.set_caching(false);
+        self
+    }
+}
+
+/// Creates input node of the compute graph with a given name. The value type is inferred from the usage
+/// of the input, or can be given explicitly, e.g. `create_input::<f64>("x")`.
+pub fn create_input<T: Copy + Default>(name: &str) -> Rc<Input<'_, T>>{
     Rc::new( Input::new(name) )
 }
 
+/// Creates input node with a given name reading its value from `buffer[index]`, see [`SliceInput`].
+pub fn create_slice_input(buffer: Rc<RefCell<Vec<f32>>>, index: usize, name: &str) -> Rc<SliceInput<'_>> {
+    Rc::new(SliceInput::new(buffer, index, name))
+}
+
+/// Creates constant node of the compute graph with a given value
+pub fn constant<T: Copy + ToString + 'static>(value: T) -> Rc<dyn Node<Output = T>> {
+    Rc::new( Constant::new(value) )
+}
+
+/// Creates binary node of the operation generic over the value type, that knows the partial derivatives
+/// `partials` of the operation on `f32` values. The gradients are computed only for `f32` graphs,
+/// so the nodes of the other types do not keep the partial derivatives.
+pub(crate) fn generic_binary<F: Fn(T, T) -> T + 'static, T: Copy + 'static>(
+    label: &'static str, x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>, op: F, partials: fn(f32, f32) -> [f32; 2]
+) -> Rc<dyn Node<Output = T>> {
+    match (&partials as &dyn Any).downcast_ref::<Partials<T>>() {
+        Some(&partials) => Binary::with_partials(label, x, y, op, partials),
+        None => Binary::named(label, x, y, op)
+    }
+}
+
 /// Creates summation node that add outputs of two given nodes and cache it.
-pub fn add(x: Rc<dyn Node<Output = f32>>, y: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
-    Binary::new(x, y, |x,y| x+y)
+pub fn add<T: Copy + Add<Output = T> + 'static>(x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>) -> Rc<dyn Node<Output = T>> {
+    generic_binary("add", x, y, |x,y| x+y, |_, _| [1.0, 1.0])
+}
+
+/// Creates subtraction node that subtract output of the second node from output of the first node and cache it.
+pub fn sub<T: Copy + Sub<Output = T> + 'static>(x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>) -> Rc<dyn Node<Output = T>> {
+    generic_binary("sub", x, y, |x,y| x-y, |_, _| [1.0, -1.0])
 }
 
 /// Creates multiplication node that multiply outputs of two given nodes and cache it.
-pub fn mul(x: Rc<dyn Node<Output = f32>>, y: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
-    Binary::new(x, y, |x,y| x*y)
+pub fn mul<T: Copy + Mul<Output = T> + 'static>(x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>) -> Rc<dyn Node<Output = T>> {
+    generic_binary("mul", x, y, |x,y| x*y, |x, y| [y, x])
+}
+
+/// Creates division node that divide output of the first node by output of the second node and cache it.
+pub fn div<T: Copy + Div<Output = T> + 'static>(x: Rc<dyn Node<Output = T>>, y: Rc<dyn Node<Output = T>>) -> Rc<dyn Node<Output = T>> {
+    generic_binary("div", x, y, |x,y| x/y, |x, y| [1.0 / y, -x / (y * y)])
+}
+
+/// Creates node of the absolute error `|approx - exact|` between the values of two nodes.
+pub fn abs_error(approx: Rc<dyn Node<Output = f32>>, exact: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Binary::named("abs_error", approx, exact, |a, e| (a - e).abs())
+}
+
+/// Creates node of the relative error `|approx - exact| / |exact|` between the values of two nodes.
+/// When the exact value is zero the absolute error is returned instead to avoid the division by zero.
+pub fn rel_error(approx: Rc<dyn Node<Output = f32>>, exact: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Binary::named("rel_error", approx, exact, |a, e| {
+        if e == 0.0 { (a - e).abs() } else { (a - e).abs() / e.abs() }
+    })
+}
+
+/// Creates node of the division `x / y` of the values of two nodes that returns `on_zero` instead of
+/// the infinite or NaN value when `y` is zero.
+pub fn safe_div(x: Rc<dyn Node<Output = f32>>, y: Rc<dyn Node<Output = f32>>, on_zero: f32) -> Rc<dyn Node<Output = f32>> {
+    Binary::named("safe_div", x, y, move |x, y| if y == 0.0 { on_zero } else { x / y })
+}
+
+/// Creates node of the saturating difference `max(x - y, 0)` of the values of two nodes.
+pub fn sub_sat(x: Rc<dyn Node<Output = f32>>, y: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Binary::named("sub_sat", x, y, |x, y| (x - y).max(0.0))
+}
+
+/// Creates node of the phase `atan2(im, re)` of the complex number with the real part `re` and the imaginary
+/// part `im`, that is the angle in `[-pi, pi]`.
+pub fn phase(re: Rc<dyn Node<Output = f32>>, im: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Binary::named("phase", re, im, |re, im| im.atan2(re))
 }
 
 /// Creates new node that compute trigonometric sinus of a value of a given nodes and cache it.
 pub fn sin(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
-    Unary::new(x, |x| x.sin())
+    Unary::with_derivative("sin", x, |x| x.sin(), f32::cos)
+}
+
+/// Logistic function `1 / (1 + exp(-x))` that does not overflow for the large `|x|`.
+fn logistic(x: f32) -> f32 {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// Creates node of the logistic sigmoid `1 / (1 + exp(-x))` of the value of some node.
+pub fn sigmoid(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Unary::with_derivative("sigmoid", x, logistic, |x| logistic(x) * (1.0 - logistic(x)))
+}
+
+/// Creates node of the SiLU (swish) activation `x * sigmoid(x)` of the value of some node.
+pub fn silu(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Unary::with_derivative("silu", x, |x| x * logistic(x), |x| {
+        let s = logistic(x);
+        s * (1.0 + x * (1.0 - s))
+    })
+}
+
+/// Creates node of the rectified linear unit `max(x, 0)` of the value of some node.
+pub fn relu(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Unary::with_derivative("relu", x, |x| x.max(0.0), |x| if x > 0.0 { 1.0 } else { 0.0 })
+}
+
+/// Creates node of the hyperbolic tangent of the value of some node.
+pub fn tanh(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Unary::with_derivative("tanh", x, f32::tanh, |x| 1.0 - x.tanh() * x.tanh())
+}
+
+/// Tanh approximation of the Gaussian error linear unit `0.5 * x * (1 + tanh(sqrt(2 / pi) * (x + 0.044715 * x^3)))`.
+fn gelu_tanh(x: f32) -> f32 {
+    let k = (2.0 / std::f32::consts::PI).sqrt();
+    0.5 * x * (1.0 + (k * (x + 0.044715 * x * x * x)).tanh())
+}
+
+/// Creates node of the Gaussian error linear unit of the value of some node in the tanh approximation
+/// `0.5 * x * (1 + tanh(sqrt(2 / pi) * (x + 0.044715 * x^3)))`.
+pub fn gelu(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Unary::named("gelu", x, gelu_tanh)
+}
+
+/// Kind of the activation function of [`activation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivationKind {
+    /// Rectified linear unit `max(x, 0)`.
+    Relu,
+    /// Logistic sigmoid `1 / (1 + exp(-x))`.
+    Sigmoid,
+    /// Hyperbolic tangent.
+    Tanh,
+    /// SiLU `x * sigmoid(x)`.
+    Silu,
+    /// Gaussian error linear unit in the tanh approximation.
+    Gelu
+}
+
+impl ActivationKind {
+    /// Returns the label of the node of the activation.
+    fn label(self) -> &'static str {
+        match self {
+            ActivationKind::Relu => "relu",
+            ActivationKind::Sigmoid => "sigmoid",
+            ActivationKind::Tanh => "tanh",
+            ActivationKind::Silu => "silu",
+            ActivationKind::Gelu => "gelu"
+        }
+    }
+
+    /// Applies the activation to the value.
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            ActivationKind::Relu => x.max(0.0),
+            ActivationKind::Sigmoid => logistic(x),
+            ActivationKind::Tanh => x.tanh(),
+            ActivationKind::Silu => x * logistic(x),
+            ActivationKind::Gelu => gelu_tanh(x)
+        }
+    }
+}
+
+/// Creates node of the activation function of a given kind on the value of some node,
+/// so the activation can be chosen by the configuration. The node is labelled by the kind, e.g. `relu`.
+pub fn activation(x: Rc<dyn Node<Output = f32>>, kind: ActivationKind) -> Rc<dyn Node<Output = f32>> {
+    Unary::named(kind.label(), x, move |x| kind.apply(x))
 }
 
 /// Creates new node that apply power function with a given exponent e to the value of some node.
+/// The exponent is the constant input of the node.
 pub fn pow_f32(x: Rc<dyn Node<Output = f32>>, e: f32) -> Rc<dyn Node<Output = f32>> {
-    Unary::new(x, move |x| f32::powf(x, e))
+    pow(x, constant(e))
+}
+
+/// Creates new node that raise the value of some node to the power given by the value of another node.
+pub fn pow(x: Rc<dyn Node<Output = f32>>, e: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Binary::with_partials("pow", x, e, f32::powf, |x, e| [e * x.powf(e - 1.0), x.powf(e) * x.ln()])
+}
+
+/// Creates new node that squares the value of some node preserving its sign, i.e. computes `x * |x|`.
+pub fn signed_square(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Unary::with_derivative("signed_square", x, |x| x * x.abs(), |x| 2.0 * x.abs())
+}
+
+/// Creates new node of the reciprocal `1 / x` of the value of some node with `|x|` floored to `eps`,
+/// preserving the sign of `x`, so the result is bounded by `1 / eps`.
+pub fn safe_recip(x: Rc<dyn Node<Output = f32>>, eps: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(eps > 0.0, "eps must be positive");
+    Unary::named("safe_recip", x, move |x| 1.0 / x.abs().max(eps).copysign(x))
+}
+
+/// Creates node of the derivative `op'(x)` of the operation of a single-variable node `op(x)` at its input `x`.
+/// Returns `None` if the node is not single-variable or its operation does not know the derivative.
+pub fn derivative_node(node: &Rc<dyn Node<Output = f32>>) -> Option<Rc<dyn Node<Output = f32>>> {
+    let deriv = node.derivative()?;
+    match node.children().as_slice() {
+        [x] => Some(Unary::named("derivative", x.clone(), deriv)),
+        _ => None
+    }
+}
+
+/// Creates new node that snaps the value of some node to the nearest element of `levels`.
+/// Ties are resolved toward the lower level. The levels are sorted once to use the binary search.
+pub fn snap_to_set(x: Rc<dyn Node<Output = f32>>, mut levels: Vec<f32>) -> Rc<dyn Node<Output = f32>> {
+    assert!(!levels.is_empty(), "set of levels must not be empty");
+    levels.sort_by(f32::total_cmp);
+    Unary::named("snap_to_set", x, move |x| {
+        let i = levels.partition_point(|&l| l < x);
+        match (i.checked_sub(1).map(|j| levels[j]), levels.get(i)) {
+            (Some(lo), Some(&hi)) => if x - lo <= hi - x { lo } else { hi },
+            (Some(lo), None) => lo,
+            (None, Some(&hi)) => hi,
+            (None, None) => unreachable!()
+        }
+    })
+}
+
+/// Interpolation mode of the reading of the table between its samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterpMode {
+    /// Take the nearest sample.
+    Nearest,
+    /// Interpolate linearly between two neighbour samples.
+    Linear
+}
+
+/// Creates new node that reads `table` at the phase given by the value of some node. The phase is wrapped
+/// to `[0, 1)`, that covers the whole table, and the reading wraps around from the last sample to the first one.
+pub fn wavetable(phase: Rc<dyn Node<Output = f32>>, table: Vec<f32>, mode: InterpMode) -> Rc<dyn Node<Output = f32>> {
+    assert!(!table.is_empty(), "table must not be empty");
+    Unary::named("wavetable", phase, move |phase| {
+        let len = table.len();
+        let pos = phase.rem_euclid(1.0) * len as f32;
+        match mode {
+            InterpMode::Nearest => table[pos.round() as usize % len],
+            InterpMode::Linear => {
+                let i = pos.floor() as usize % len;
+                let frac = pos - pos.floor();
+                table[i] * (1.0 - frac) + table[(i + 1) % len] * frac
+            }
+        }
+    })
+}
+
+/// Creates node of the one-hot vector of length `size` with `1.0` at the position of the rounded value
+/// of `index` and `0.0` elsewhere. The index out of `[0, size)` yields all zeros.
+/// The node is a [`Map`], so the traversals of the vector graph stop at it and do not reach `index`.
+pub fn one_hot(index: Rc<dyn Node<Output = f32>>, size: usize) -> Rc<dyn Node<Output = Vec<f32>>> {
+    Map::named("one_hot", index, move |i: f32| {
+        let mut v = vec![0.0; size];
+        let i = i.round();
+        if i >= 0.0 && (i as usize) < size {
+            v[i as usize] = 1.0;
+        }
+        v
+    })
+}
+
+/// Creates node of the L1 norm `sum(|v_i|)` of the value of a vector node. The norm of the empty vector is `0.0`.
+/// The vector node is not a child of the resulting [`Map`] node, so the traversals of the graph do not reach it.
+pub fn l1_norm(v: Rc<dyn Node<Output = Vec<f32>>>) -> Rc<dyn Node<Output = f32>> {
+    Map::named("l1_norm", v, |v: Vec<f32>| v.iter().map(|x| x.abs()).sum())
+}
+
+/// Creates node of the L2 norm `sqrt(sum(v_i^2))` of the value of a vector node. The norm of the empty vector is `0.0`.
+/// Like [`l1_norm`], the node does not list the vector node in its children.
+pub fn l2_norm(v: Rc<dyn Node<Output = Vec<f32>>>) -> Rc<dyn Node<Output = f32>> {
+    Map::named("l2_norm", v, |v: Vec<f32>| v.iter().map(|x| x * x).sum::<f32>().sqrt())
+}
+
+/// Creates new node that raise the value of some node to the integer power n.
+/// It uses `f32::powi`, that is faster than `pow_f32` and exact for small integer powers.
+pub fn powi(x: Rc<dyn Node<Output = f32>>, n: i32) -> Rc<dyn Node<Output = f32>> {
+    Unary::named("powi", x, move |x| x.powi(n))
+}
+
+/// Creates node that sums the outputs of the given nodes and clamps the sum to `[0, 1]`,
+/// e.g. to accumulate probability-like values.
+pub fn sum_clamped01(nodes: Vec<Rc<dyn Node<Output = f32>>>) -> Rc<dyn Node<Output = f32>> {
+    NAry::named("sum_clamped01", nodes, |xs| xs.iter().sum::<f32>().clamp(0.0, 1.0))
+}
+
+/// Creates node of the harmonic mean `n / sum(1 / x_i)` of the values of the given nodes.
+/// If any of the values is zero, the harmonic mean is `0.0`.
+pub fn harmonic_mean(nodes: Vec<Rc<dyn Node<Output = f32>>>) -> Rc<dyn Node<Output = f32>> {
+    NAry::named("harmonic_mean", nodes, |xs| {
+        if xs.contains(&0.0) {
+            return 0.0;
+        }
+        xs.len() as f32 / xs.iter().map(|x| 1.0 / x).sum::<f32>()
+    })
+}
+
+/// Restores the saved values of the inputs on drop, in the reverse order of the overrides.
+struct RestoreInputs<'r, 'a, T: Copy + PartialEq> {
+    saved: Vec<(&'r Rc<Input<'a, T>>, T)>
+}
+
+impl<T: Copy + PartialEq> Drop for RestoreInputs<'_, '_, T> {
+    fn drop(&mut self) {
+        for (x, v) in self.saved.iter().rev() {
+            x.set(*v);
+        }
+    }
+}
+
+/// Temporarily sets the given values to the inputs, runs `f` and then restores the previous values of the inputs,
+/// also if `f` panics. The caches of the dependent nodes are invalidated on both changes, so the graph returns
+/// to its previous state. The graph is not passed to the function, `f` computes the nodes it needs.
+pub fn with_overrides<T: Copy + PartialEq, R>(overrides: &[(&Rc<Input<T>>, T)], f: impl FnOnce() -> R) -> R {
+    let _restore = RestoreInputs { saved: overrides.iter().map(|&(x, _)| (x, x.compute())).collect() };
+    for (x, v) in overrides {
+        x.set(*v);
+    }
+    f()
+}
+
+/// Marks all the given inputs changed with a single invalidation pass, so the dependent nodes shared
+/// by several inputs are invalidated once.
+pub fn invalidate_inputs<T: Copy>(inputs: &[&Rc<Input<T>>]) {
+    invalidation_pass(|| {
+        for x in inputs {
+            x.invalidate();
+        }
+    });
+}
+
+/// Returns the iterator that lazily takes the rows of the input values from `source`, sets them to `inputs`
+/// and yields the value of `root` for each row. The caches are kept between the rows, so only the nodes
+/// depending on the changed inputs are recomputed.
+pub fn stream_eval<'a>(
+    root: &Rc<dyn Node<Output = f32>>,
+    inputs: &[Rc<Input<'a>>],
+    source: impl Iterator<Item = Vec<f32>> + 'a
+) -> impl Iterator<Item = f32> + 'a {
+    let (root, inputs) = (root.clone(), inputs.to_vec());
+    source.map(move |row| {
+        assert_eq!(row.len(), inputs.len(), "row must match the inputs");
+        for (x, v) in inputs.iter().zip(row) {
+            x.set(v);
+        }
+        root.compute()
+    })
+}
+
+/// Vector of the scalar inputs, that are set all at once with a single invalidation pass.
+pub struct InputVector<'a, T: Copy = f32> {
+    inputs: Vec<Rc<Input<'a, T>>>
+}
+
+impl<'a, T: Copy + Default + PartialEq> InputVector<'a, T> {
+    /// Creates the vector of the inputs with the given names.
+    pub fn new(names: &[&'a str]) -> Self {
+        InputVector { inputs: names.iter().map(|&name| Rc::new(Input::new(name))).collect() }
+    }
+
+    /// Returns the number of the inputs.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Returns `true` if the vector has no inputs.
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Returns the `i`-th input node.
+    pub fn get(&self, i: usize) -> Rc<Input<'a, T>> {
+        self.inputs[i].clone()
+    }
+
+    /// Sets the values of all the inputs, invalidating the dependent nodes shared by several inputs once.
+    pub fn set_all(&self, values: &[T]) {
+        assert_eq!(values.len(), self.inputs.len(), "values must match the inputs");
+        invalidation_pass(|| {
+            for (x, &v) in self.inputs.iter().zip(values) {
+                x.set(v);
+            }
+        });
+    }
+}
+
+/// Creates node of the soft argmax of the values of the given nodes: the index weighted by the softmax
+/// of the values scaled by `1 / temperature`, i.e. `sum(i * softmax(x / T)_i)`. Low temperature makes
+/// it close to the index of the maximum value.
+pub fn soft_argmax(nodes: Vec<Rc<dyn Node<Output = f32>>>, temperature: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(temperature > 0.0, "temperature must be positive");
+    NAry::named("soft_argmax", nodes, move |xs| {
+        softmax(xs, temperature).iter().enumerate().map(|(i, p)| i as f32 * p).sum()
+    })
+}
+
+/// Softmax of the values scaled by `1 / temperature`.
+fn softmax(xs: &[f32], temperature: f32) -> Vec<f32> {
+    // The maximum is subtracted for the numerical stability of the exponents.
+    let max = xs.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f32> = xs.iter().map(|x| ((x - max) / temperature).exp()).collect();
+    let total: f32 = weights.iter().sum();
+    weights.iter().map(|w| w / total).collect()
+}
+
+/// Creates node of the Shannon entropy `-sum(p_i * ln(p_i))` of the softmax `p` of the values of the given nodes
+/// scaled by `1 / temperature`. The entropy is `ln(n)` for the equal values and goes to zero as one value dominates.
+pub fn softmax_entropy(nodes: Vec<Rc<dyn Node<Output = f32>>>, temperature: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(temperature > 0.0, "temperature must be positive");
+    NAry::named("softmax_entropy", nodes, move |xs| {
+        // The zero probabilities do not contribute, as the limit of `p * ln(p)` is zero.
+        -softmax(xs, temperature).iter().filter(|&&p| p > 0.0).map(|p| p * p.ln()).sum::<f32>()
+    })
+}
+
+/// Creates node of the bilinear interpolation of the corner values `q00`, `q01`, `q10`, `q11` by the parameters
+/// `tx` and `ty`, where `qij` is the value at `tx = i`, `ty = j`. The inputs of the node are in the order
+/// of the arguments. The parameters are not clamped, so the values outside of `[0, 1]` extrapolate.
+pub fn bilinear(
+    q00: Rc<dyn Node<Output = f32>>,
+    q01: Rc<dyn Node<Output = f32>>,
+    q10: Rc<dyn Node<Output = f32>>,
+    q11: Rc<dyn Node<Output = f32>>,
+    tx: Rc<dyn Node<Output = f32>>,
+    ty: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    NAry::named("bilinear", vec![q00, q01, q10, q11, tx, ty], |xs| {
+        let [q00, q01, q10, q11, tx, ty] = [xs[0], xs[1], xs[2], xs[3], xs[4], xs[5]];
+        let y0 = q00 + (q01 - q00) * ty;
+        let y1 = q10 + (q11 - q10) * ty;
+        y0 + (y1 - y0) * tx
+    })
+}
+
+/// Creates node of the smooth maximum `1 / beta * ln(sum(exp(beta * x_i)))` of the values of the given nodes.
+/// The result approaches the maximum as `beta` grows and is smooth in the values for any finite `beta`.
+pub fn soft_max(nodes: Vec<Rc<dyn Node<Output = f32>>>, beta: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(beta > 0.0, "beta must be positive");
+    NAry::named("soft_max", nodes, move |xs| {
+        // The maximum is subtracted for the numerical stability of the exponents.
+        let max = xs.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        max + xs.iter().map(|x| (beta * (x - max)).exp()).sum::<f32>().ln() / beta
+    })
+}
+
+/// Creates node of the smooth minimum `-1 / beta * ln(sum(exp(-beta * x_i)))` of the values of the given nodes.
+/// The result approaches the minimum as `beta` grows and is smooth in the values for any finite `beta`.
+pub fn soft_min(nodes: Vec<Rc<dyn Node<Output = f32>>>, beta: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(beta > 0.0, "beta must be positive");
+    NAry::named("soft_min", nodes, move |xs| {
+        // The minimum is subtracted for the numerical stability of the exponents.
+        let min = xs.iter().cloned().fold(f32::INFINITY, f32::min);
+        min - xs.iter().map(|x| (-beta * (x - min)).exp()).sum::<f32>().ln() / beta
+    })
+}
+
+/// Creates node that linearly maps the value of `x` from the range `[in_lo, in_hi]` to the range `[out_lo, out_hi]`
+/// and clamps the result to the output range. Empty input range (`in_lo == in_hi`) maps everything to `out_lo`.
+pub fn map_range(
+    x: Rc<dyn Node<Output = f32>>,
+    in_lo: Rc<dyn Node<Output = f32>>,
+    in_hi: Rc<dyn Node<Output = f32>>,
+    out_lo: Rc<dyn Node<Output = f32>>,
+    out_hi: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    NAry::named("map_range", vec![x, in_lo, in_hi, out_lo, out_hi], |xs| {
+        let [x, in_lo, in_hi, out_lo, out_hi] = [xs[0], xs[1], xs[2], xs[3], xs[4]];
+        if in_lo == in_hi {
+            return out_lo;
+        }
+        let y = out_lo + (x - in_lo) / (in_hi - in_lo) * (out_hi - out_lo);
+        y.clamp(out_lo.min(out_hi), out_lo.max(out_hi))
+    })
+}
+
+/// Creates stateful node that compares the value of a given node with its value at the previous recomputation
+/// and returns `1` if it increased, `-1` if it decreased and `0` if it stayed the same (or on the first computation).
+pub fn trend(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("trend", vec![x], None, |prev: &mut Option<f32>, xs| {
+        match prev.replace(xs[0]) {
+            Some(p) if xs[0] > p => 1.0,
+            Some(p) if xs[0] < p => -1.0,
+            _ => 0.0
+        }
+    })
+}
+
+/// Creates stateful node of one-pole low-pass filter with a given `cutoff` frequency and time step `dt`.
+/// On each recomputation the filter state moves toward the value of a given node
+/// by a factor `dt / (RC + dt)`, where `RC = 1 / (2 * PI * cutoff)`. The initial state is zero.
+pub fn lowpass(x: Rc<dyn Node<Output = f32>>, cutoff: f32, dt: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(cutoff > 0.0, "cutoff frequency must be positive");
+    assert!(dt > 0.0, "time step must be positive");
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+    let alpha = dt / (rc + dt);
+    Stateful::named("lowpass", vec![x], 0.0, move |y: &mut f32, xs| {
+        *y += alpha * (xs[0] - *y);
+        *y
+    })
+}
+
+/// Creates node of the signed distance from the value of `x` to the range `[lo, hi]`: it is `0` inside the range,
+/// negative `x - lo` below the range and positive `x - hi` above the range.
+pub fn range_distance(
+    x: Rc<dyn Node<Output = f32>>,
+    lo: Rc<dyn Node<Output = f32>>,
+    hi: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    Ternary::named("range_distance", x, lo, hi, |x, lo, hi| {
+        if x < lo {
+            x - lo
+        } else if x > hi {
+            x - hi
+        } else {
+            0.0
+        }
+    })
+}
+
+/// Creates node that blends the values of `a` and `b` as `(1 - t) * a + t * b`.
+/// Unlike the plain linear interpolation, the value of `t` is clamped to `[0, 1]`,
+/// so the result always stays between the values of `a` and `b`.
+pub fn crossfade(
+    a: Rc<dyn Node<Output = f32>>,
+    b: Rc<dyn Node<Output = f32>>,
+    t: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    Ternary::named("crossfade", a, b, t, |a, b, t| {
+        let t = t.clamp(0.0, 1.0);
+        (1.0 - t) * a + t * b
+    })
+}
+
+/// Creates node that wraps the value of `x` into the range `[lo, hi)` modulo its width, e.g. for the toroidal
+/// coordinates. The values below the range wrap to its top, so `wrap(-1, 0, 5)` is `4`. The result is NaN
+/// if the range is empty, i.e. `hi <= lo`.
+pub fn wrap(
+    x: Rc<dyn Node<Output = f32>>,
+    lo: Rc<dyn Node<Output = f32>>,
+    hi: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    Ternary::named("wrap", x, lo, hi, |x, lo, hi| {
+        if hi <= lo {
+            return f32::NAN;
+        }
+        let width = hi - lo;
+        let r = (x - lo).rem_euclid(width);
+        // The remainder of tiny negative values rounds up to the width.
+        if r >= width { lo } else { lo + r }
+    })
+}
+
+/// Creates node of the normalized weighted blend `sum(w_i * x_i) / sum(w_i)` of the values of `nodes`
+/// with the values of `weights` nodes. Returns `0.0` when the total weight is zero.
+pub fn blend(nodes: Vec<Rc<dyn Node<Output = f32>>>, weights: Vec<Rc<dyn Node<Output = f32>>>) -> Rc<dyn Node<Output = f32>> {
+    assert_eq!(nodes.len(), weights.len(), "number of weights must match number of nodes");
+    let n = nodes.len();
+    NAry::named("blend", nodes.into_iter().chain(weights).collect(), move |xs| {
+        let (xs, ws) = xs.split_at(n);
+        let total: f32 = ws.iter().sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+        xs.iter().zip(ws).map(|(x, w)| x * w).sum::<f32>() / total
+    })
+}
+
+/// Creates indicator node that returns `1` if the values of `x` and `y` differ at most by `tol`, and `0` otherwise.
+pub fn approx_eq(
+    x: Rc<dyn Node<Output = f32>>,
+    y: Rc<dyn Node<Output = f32>>,
+    tol: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    Ternary::named("approx_eq", x, y, tol, |x, y, tol| if (x - y).abs() <= tol { 1.0 } else { 0.0 })
+}
+
+/// Creates stateful node of exponential moving average with a time constant `tau`, that handles irregular time steps.
+/// On each recomputation the state moves toward the value of `x` by a factor `1 - exp(-dt / tau)`,
+/// where `dt` is the value of the time step node. The initial state is zero.
+pub fn ema_time(x: Rc<dyn Node<Output = f32>>, tau: f32, dt: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    assert!(tau > 0.0, "time constant must be positive");
+    Stateful::named("ema_time", vec![x, dt], 0.0, move |y: &mut f32, xs| {
+        let alpha = 1.0 - (-xs[1] / tau).exp();
+        *y += alpha * (xs[0] - *y);
+        *y
+    })
+}
+
+/// Creates stateful node integrating the value of `x` over the wall-clock time: on each recomputation
+/// it adds `x * dt` to the accumulated value, where `dt` is the time in seconds elapsed since the previous
+/// recomputation (zero for the first one). The result depends on the timing of the recomputations,
+/// which happen only when the node is invalidated and its value is requested.
+pub fn integrate_time(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    let start = Instant::now();
+    integrate_time_with(x, move || start.elapsed().as_secs_f64())
+}
+
+/// Creates the node of [`integrate_time`] that reads the time in seconds from `clock` instead of the system clock,
+/// e.g. the simulation time.
+pub fn integrate_time_with(
+    x: Rc<dyn Node<Output = f32>>,
+    clock: impl Fn() -> f64 + 'static
+) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("integrate_time", vec![x], (None, 0.0), move |s: &mut (Option<f64>, f32), xs| {
+        let now = clock();
+        if let Some(prev) = s.0 {
+            s.1 += xs[0] * (now - prev) as f32;
+        }
+        s.0 = Some(now);
+        s.1
+    })
+}
+
+/// Dependent nodes detached from some node with [`detach`].
+pub struct DetachedDeps(Vec<Weak<dyn Dependent>>);
+
+/// Detach a given node from all its dependent nodes, so the changes of the node are not propagated to them.
+/// The dependent nodes keep their cached values until the node is reattached with [`reattach`].
+pub fn detach<T>(node: &Rc<dyn Node<Output = T>>) -> DetachedDeps {
+    DetachedDeps(node.take_dependents())
+}
+
+/// Reattach previously detached dependent nodes to a given node and invalidate them,
+/// so they take into account the changes made during detachment.
+pub fn reattach<T>(node: &Rc<dyn Node<Output = T>>, deps: DetachedDeps) {
+    node.restore_dependents(deps.0);
+    node.invalidate();
+}
+
+/// Creates stateful node of the logistic map: on each recomputation the state is iterated once
+/// as `x = r * x * (1 - x)`, where `r` is the value of a given node, starting from the `seed`.
+/// The node returns the new state, so the sequence advances every time the node is invalidated and computed.
+pub fn logistic_map(r: Rc<dyn Node<Output = f32>>, seed: f32) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("logistic_map", vec![r], seed, |x: &mut f32, xs| {
+        *x = xs[0] * *x * (1.0 - *x);
+        *x
+    })
+}
+
+thread_local! {
+    static SEED: Cell<u64> = const { Cell::new(0) };
+    static STOCHASTIC: Dependencies = Default::default();
+}
+
+/// Replace the seed of the stochastic nodes of the current thread and invalidate them.
+fn set_seed(seed: u64) -> u64 {
+    let previous = SEED.with(|s| s.replace(seed));
+    STOCHASTIC.with(|d| d.invalidate());
+    previous
+}
+
+/// Seed replaced by [`with_seed`], restored on drop.
+struct RestoreSeed(u64);
+
+impl Drop for RestoreSeed {
+    fn drop(&mut self) {
+        set_seed(self.0);
+    }
+}
+
+/// Runs `f` with a given seed of the stochastic nodes of the current thread and then restores the previous seed,
+/// also if `f` panics. The stochastic nodes compute the same values under the same seed, so the runs are reproducible.
+pub fn with_seed<R>(seed: u64, f: impl FnOnce() -> R) -> R {
+    let _restore = RestoreSeed(set_seed(seed));
+    f()
+}
+
+/// Creates stochastic node of the pseudo-random noise in `[0, 1)`, that is a hash of the value of a given node
+/// and the current seed (see [`with_seed`]). The change of the seed invalidates the node.
+pub fn hash_noise(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    let n = Unary::named("hash_noise", x, |x| {
+        // splitmix64 finalizer of the value bits mixed with the seed
+        let mut h = (x.to_bits() as u64) ^ SEED.with(|s| s.get()).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+        (h >> 40) as f32 / (1u64 << 24) as f32
+    });
+    STOCHASTIC.with(|d| d.add(n.clone()));
+    n
+}
+
+/// Creates stateful node of the running product of the values of a given node: on each recomputation
+/// the product, starting from `1.0`, is multiplied by the value of the node. To avoid the overflow to infinity
+/// the magnitude of the product is clamped to `max`, so the product stays in `[-max, max]`.
+pub fn running_product(x: Rc<dyn Node<Output = f32>>, max: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(max > 0.0, "max magnitude must be positive");
+    Stateful::named("running_product", vec![x], 1.0, move |p: &mut f32, xs| {
+        *p = (*p * xs[0]).clamp(-max, max);
+        *p
+    })
+}
+
+/// Creates stateful node of the PID controller output `kp * e + ki * sum(e * dt) + kd * de / dt` for the error
+/// `e = setpoint - measurement`. The inputs of the node are ordered as
+/// `[setpoint, measurement, kp, ki, kd, dt]`. On each recomputation the integral of the error is accumulated
+/// and the error is stored for the derivative term of the next recomputation. The derivative term is zero
+/// on the first recomputation and for the zero time step.
+pub fn pid(
+    setpoint: Rc<dyn Node<Output = f32>>,
+    measurement: Rc<dyn Node<Output = f32>>,
+    kp: Rc<dyn Node<Output = f32>>,
+    ki: Rc<dyn Node<Output = f32>>,
+    kd: Rc<dyn Node<Output = f32>>,
+    dt: Rc<dyn Node<Output = f32>>
+) -> Rc<dyn Node<Output = f32>> {
+    let xs = vec![setpoint, measurement, kp, ki, kd, dt];
+    Stateful::named("pid", xs, (0.0, None), |s: &mut (f32, Option<f32>), xs| {
+        let (kp, ki, kd, dt) = (xs[2], xs[3], xs[4], xs[5]);
+        let e = xs[0] - xs[1];
+        s.0 += e * dt;
+        let de = match s.1 {
+            Some(prev) if dt != 0.0 => (e - prev) / dt,
+            _ => 0.0
+        };
+        s.1 = Some(e);
+        kp * e + ki * s.0 + kd * de
+    })
+}
+
+/// Creates stateful node of the leaky integrator: on each recomputation the state, starting from zero,
+/// is updated as `state = state * (1 - leak) + x`, where `x` is the value of a given node. For the constant
+/// input the state converges to `x / leak`.
+pub fn leaky_integrator(x: Rc<dyn Node<Output = f32>>, leak: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!((0.0..=1.0).contains(&leak), "leak must be in [0, 1]");
+    Stateful::named("leaky_integrator", vec![x], 0.0, move |state: &mut f32, xs| {
+        *state = *state * (1.0 - leak) + xs[0];
+        *state
+    })
+}
+
+/// Creates stateful node of the geometric decay schedule: on the `n`-th recomputation, counting from zero,
+/// it returns `initial * rate^n`, where `initial` is the value of a given node. So the schedule steps
+/// on each invalidation of `initial`, even if its value stays the same.
+pub fn decay_schedule(initial: Rc<dyn Node<Output = f32>>, rate: f32) -> Rc<dyn Node<Output = f32>> {
+    assert!(rate > 0.0 && rate < 1.0, "rate must be in (0, 1)");
+    Stateful::named("decay_schedule", vec![initial], 0, move |n: &mut i32, xs| {
+        let res = xs[0] * rate.powi(*n);
+        *n += 1;
+        res
+    })
+}
+
+/// Creates stateful node detecting the crossings of `threshold` by the value of `x`: on each recomputation
+/// it returns `1.0` if `x` went from below the threshold to the threshold or above since the previous
+/// recomputation, `-1.0` if it went from the threshold or above to below, and `0.0` otherwise.
+/// The node keeps the value of `x` of the previous recomputation, there is no crossing on the first one.
+pub fn crossing(x: Rc<dyn Node<Output = f32>>, threshold: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("crossing", vec![x, threshold], None, |prev: &mut Option<f32>, xs| {
+        let (x, threshold) = (xs[0], xs[1]);
+        let res = match *prev {
+            Some(p) if p < threshold && x >= threshold => 1.0,
+            Some(p) if p >= threshold && x < threshold => -1.0,
+            _ => 0.0
+        };
+        *prev = Some(x);
+        res
+    })
+}
+
+/// Creates stateful node counting the events: on each recomputation it adds the value of `increment`
+/// to the running total, or resets the total to zero when the value of `reset` is nonzero.
+pub fn counter(increment: Rc<dyn Node<Output = f32>>, reset: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("counter", vec![increment, reset], 0.0, |total: &mut f32, xs| {
+        *total = if xs[1] != 0.0 { 0.0 } else { *total + xs[0] };
+        *total
+    })
+}
+
+/// Creates stateful node of the first difference `x_t - x_{t-1}` between the value of a given node
+/// at the current and the previous recomputations. The previous value before the first recomputation is `seed`.
+pub fn diff(x: Rc<dyn Node<Output = f32>>, seed: f32) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("diff", vec![x], seed, |prev: &mut f32, xs| {
+        let d = xs[0] - *prev;
+        *prev = xs[0];
+        d
+    })
+}
+
+/// Creates stateful node of the maximum drawdown: it tracks the running peak of the value of a given node
+/// over the recomputations and returns the largest decline from a peak to a subsequent value observed so far.
+pub fn max_drawdown(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("max_drawdown", vec![x], (f32::NEG_INFINITY, 0.0), |(peak, drawdown): &mut (f32, f32), xs| {
+        *peak = peak.max(xs[0]);
+        *drawdown = drawdown.max(*peak - xs[0]);
+        *drawdown
+    })
+}
+
+/// Creates stateful node of the logarithm of the running product of the values of a given node: on each
+/// recomputation the node adds `ln(x)` to the sum kept by the node and returns the sum, so the product of many
+/// small values does not underflow. Zero makes the sum `-inf` and a negative value makes it NaN, in both cases
+/// for all the subsequent recomputations. See [`exp_f64`] to get the product back.
+pub fn log_product(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("log_product", vec![x], 0.0f64, |sum: &mut f64, xs| {
+        *sum += (xs[0] as f64).ln();
+        *sum as f32
+    })
+}
+
+/// Creates node of the exponent `exp(x)` of the value of some node in the double precision, e.g. to get
+/// the product from [`log_product`] that would underflow `f32`. The `f32` node is not a child of the resulting
+/// [`Map`] node, so e.g. [`nodes_of`](crate::analysis::nodes_of) of the `f64` graph stops at `exp`.
+pub fn exp_f64(x: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f64>> {
+    Map::named("exp", x, |x: f32| (x as f64).exp())
+}
+
+/// Creates stateful node of the histogram of the values of a given node: on each recomputation the node
+/// increments the count of the bucket `[edges[i], edges[i + 1])` containing the value and returns the counts
+/// of all the buckets. The values outside of `[edges[0], edges[n - 1])` and NaN are not counted.
+/// The value node has another type, so it is not a child of the histogram node and the traversals stop at it.
+pub fn histogram(x: Rc<dyn Node<Output = f32>>, edges: Vec<f32>) -> Rc<dyn Node<Output = Vec<f32>>> {
+    assert!(edges.len() >= 2, "edges must define at least one bucket");
+    assert!(edges.windows(2).all(|w| w[0] < w[1]), "edges must be strictly increasing");
+    let counts = vec![0.0; edges.len() - 1];
+    Stateful::named("histogram", vec![x], counts, move |counts: &mut Vec<f32>, xs| {
+        // The index of the first edge above the value is the bucket index plus one.
+        let i = edges.partition_point(|&e| e <= xs[0]);
+        if i > 0 && i < edges.len() {
+            counts[i - 1] += 1.0;
+        }
+        counts.clone()
+    })
+}
+
+/// Creates stateful node of the running Pearson correlation of the values of `x` and `y`: on each recomputation
+/// the pair of values is added to the sums, the sums of squares and the sum of products kept by the node,
+/// and the correlation of all pairs seen so far is returned. While the variance of any of the values is zero
+/// the correlation is undefined and `0.0` is returned.
+pub fn running_correlation(x: Rc<dyn Node<Output = f32>>, y: Rc<dyn Node<Output = f32>>) -> Rc<dyn Node<Output = f32>> {
+    Stateful::named("running_correlation", vec![x, y], [0.0f64; 6], |s: &mut [f64; 6], xs| {
+        let (x, y) = (xs[0] as f64, xs[1] as f64);
+        let [n, sx, sy, sxx, syy, sxy] = s;
+        *n += 1.0;
+        *sx += x;
+        *sy += y;
+        *sxx += x * x;
+        *syy += y * y;
+        *sxy += x * y;
+        let var_x = *n * *sxx - *sx * *sx;
+        let var_y = *n * *syy - *sy * *sy;
+        if var_x <= 0.0 || var_y <= 0.0 {
+            return 0.0;
+        }
+        ((*n * *sxy - *sx * *sy) / (var_x * var_y).sqrt()).clamp(-1.0, 1.0) as f32
+    })
+}
+
+/// Creates stateful node of the moving median: it keeps the values of a given node at the last `window`
+/// recomputations and returns their median. For the even number of the values the mean of two middle values is taken.
+pub fn moving_median(x: Rc<dyn Node<Output = f32>>, window: usize) -> Rc<dyn Node<Output = f32>> {
+    assert!(window > 0, "window must not be empty");
+    Stateful::named("moving_median", vec![x], VecDeque::with_capacity(window), move |values: &mut VecDeque<f32>, xs| {
+        if values.len() == window {
+            values.pop_front();
+        }
+        values.push_back(xs[0]);
+        let mut sorted: Vec<f32> = values.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let mid = sorted.len() / 2;
+        if sorted.len() % 2 == 1 { sorted[mid] } else { (sorted[mid - 1] + sorted[mid]) / 2.0 }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::round;
+    use crate::{analysis, state, on_invalidate, clear_invalidate_callbacks};
+
+    #[test]
+    fn test_sum_clamped01() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let graph = sum_clamped01(vec![x1.clone(), x2.clone()]);
+        x1.set(0.75);
+        x2.set(0.5);
+        assert_eq!(graph.compute(), 1.0);
+        x2.set(0.125);
+        assert_eq!(graph.compute(), 0.875);
+        x1.set(-1.0);
+        assert_eq!(graph.compute(), 0.0);
+    }
+
+    #[test]
+    fn test_derivative_node() {
+        let x = create_input("x");
+        let graph = sin(x.clone());
+        let d = derivative_node(&graph).unwrap();
+        x.set(0.7);
+        assert_eq!(d.compute(), 0.7f32.cos());
+        x.set(-2.0);
+        assert_eq!(d.compute(), (-2f32).cos());
+        assert!(derivative_node(&add(x.clone(), x.clone())).is_none());
+    }
+
+    #[test]
+    fn test_harmonic_mean() {
+        let xs: Vec<_> = (0..3).map(|_| create_input::<f32>("x")).collect();
+        let graph = harmonic_mean(xs.iter().map(|x| x.clone() as Rc<dyn Node<Output = f32>>).collect());
+        for (x, v) in xs.iter().zip([1.0, 2.0, 4.0]) {
+            x.set(v);
+        }
+        assert_eq!(round(graph.compute(), 3), 1.714);
+        xs[2].set(2.0);
+        assert_eq!(round(graph.compute(), 3), 1.5);
+        xs[0].set(0.0);
+        assert_eq!(graph.compute(), 0.0);
+    }
+
+    #[test]
+    fn test_with_overrides() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let graph = mul(x1.clone(), add(x1.clone(), x2.clone()));
+        x1.set(2.0);
+        x2.set(3.0);
+        assert_eq!(graph.compute(), 10.0);
+        let res = with_overrides(&[(&x1, 1.0), (&x2, -1.0)], || graph.compute());
+        assert_eq!(res, 0.0);
+        assert_eq!(x1.compute(), 2.0);
+        assert_eq!(x2.compute(), 3.0);
+        assert_eq!(graph.compute(), 10.0);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_overrides(&[(&x1, 5.0)], || panic!("query failed"))
+        }));
+        assert!(res.is_err());
+        assert_eq!(x1.compute(), 2.0);
+        assert_eq!(graph.compute(), 10.0);
+    }
+
+    #[test]
+    fn test_trend() {
+        let x1 = create_input("x1");
+        let graph = trend(x1.clone());
+        x1.set(1.0);
+        assert_eq!(graph.compute(), 0.0);
+        x1.set(2.0);
+        assert_eq!(graph.compute(), 1.0);
+        x1.set(3.0);
+        assert_eq!(graph.compute(), 1.0);
+        x1.set(-1.0);
+        assert_eq!(graph.compute(), -1.0);
+        // the same value does not invalidate the node, so it is recomputed on explicit invalidation
+        x1.set(-1.0);
+        assert_eq!(graph.compute(), -1.0);
+        x1.invalidate();
+        assert_eq!(graph.compute(), 0.0);
+    }
+
+    #[test]
+    fn test_lowpass() {
+        let x1 = create_input("x1");
+        // RC = 1, so the smoothing factor is dt / (1 + dt)
+        let graph = lowpass(x1.clone(), 0.5 / std::f32::consts::PI, 0.1);
+        let alpha: f32 = 0.1 / 1.1;
+        x1.set(1.0);
+        for n in 1..=10 {
+            let expected = 1.0 - (1.0 - alpha).powi(n);
+            assert_eq!(round(graph.compute(), 5), round(expected, 5));
+            x1.invalidate();
+        }
+    }
+
+    #[test]
+    fn test_powi() {
+        let x1 = create_input("x1");
+        let graph = powi(x1.clone(), 10);
+        x1.set(2.0);
+        assert_eq!(graph.compute(), 1024.0);
+        x1.set(-3.0);
+        assert_eq!(graph.compute(), 59049.0);
+    }
+
+    #[test]
+    fn test_range_distance() {
+        let x = create_input("x");
+        let lo = create_input("lo");
+        let hi = create_input("hi");
+        let graph = range_distance(x.clone(), lo.clone(), hi.clone());
+        lo.set(1.0);
+        hi.set(3.0);
+        x.set(2.0);
+        assert_eq!(graph.compute(), 0.0);
+        x.set(-1.0);
+        assert_eq!(graph.compute(), -2.0);
+        x.set(5.0);
+        assert_eq!(graph.compute(), 2.0);
+        hi.set(6.0);
+        assert_eq!(graph.compute(), 0.0);
+        lo.set(5.5);
+        assert_eq!(graph.compute(), -0.5);
+    }
+
+    #[test]
+    fn test_blend() {
+        let (x1, x2) = (create_input("x1"), create_input("x2"));
+        let (w1, w2) = (create_input("w1"), create_input("w2"));
+        let graph = blend(vec![x1.clone(), x2.clone()], vec![w1.clone(), w2.clone()]);
+        x1.set(2.0);
+        x2.set(6.0);
+        assert_eq!(graph.compute(), 0.0);
+        w1.set(3.0);
+        w2.set(1.0);
+        assert_eq!(graph.compute(), 3.0);
+        w2.set(3.0);
+        assert_eq!(graph.compute(), 4.0);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let a = create_input("a");
+        let b = create_input("b");
+        let t = create_input("t");
+        let graph = crossfade(a.clone(), b.clone(), t.clone());
+        a.set(2.0);
+        b.set(4.0);
+        assert_eq!(graph.compute(), 2.0);
+        t.set(1.0);
+        assert_eq!(graph.compute(), 4.0);
+        t.set(0.25);
+        assert_eq!(graph.compute(), 2.5);
+        t.set(-3.0);
+        assert_eq!(graph.compute(), 2.0);
+        t.set(7.0);
+        assert_eq!(graph.compute(), 4.0);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let x = create_input("x");
+        let y = create_input("y");
+        let tol = create_input("tol");
+        let graph = approx_eq(x.clone(), y.clone(), tol.clone());
+        x.set(1.0);
+        y.set(1.0005);
+        tol.set(0.001);
+        assert_eq!(graph.compute(), 1.0);
+        y.set(1.1);
+        assert_eq!(graph.compute(), 0.0);
+        tol.set(0.5);
+        assert_eq!(graph.compute(), 1.0);
+    }
+
+    #[test]
+    fn test_invalidate_inputs() {
+        let (x1, x2, x3) = (create_input::<f32>("x1"), create_input("x2"), create_input("x3"));
+        let sink = add(mul(x1.clone(), x2.clone()), add(x2.clone(), x3.clone()));
+        sink.compute();
+        let count = Rc::new(Cell::new(0));
+        {
+            let (count, id) = (count.clone(), sink.id());
+            on_invalidate(Box::new(move |n| if n == id { count.set(count.get() + 1) }));
+        }
+        invalidate_inputs(&[&x1, &x2, &x3]);
+        clear_invalidate_callbacks();
+        assert_eq!(count.get(), 1);
+        assert_eq!(sink.recompute_count(), 1);
+        sink.compute();
+        assert_eq!(sink.recompute_count(), 2);
+    }
+
+    #[test]
+    fn test_input_vector() {
+        let xs = InputVector::new(&["x1", "x2", "x3"]);
+        assert_eq!(xs.len(), 3);
+        let sink = add(mul(xs.get(0), xs.get(1)), add(xs.get(1), xs.get(2)));
+        let count = Rc::new(Cell::new(0));
+        {
+            let (count, id) = (count.clone(), sink.id());
+            on_invalidate(Box::new(move |n| if n == id { count.set(count.get() + 1) }));
+        }
+        xs.set_all(&[1.0, 2.0, 3.0]);
+        clear_invalidate_callbacks();
+        assert_eq!(count.get(), 1);
+        assert_eq!(sink.compute(), 7.0);
+        assert_eq!(sink.recompute_count(), 1);
+        assert_eq!(xs.get(2).compute(), 3.0);
+    }
+
+    #[test]
+    fn test_safe_div() {
+        let x = create_input("x");
+        let y = create_input("y");
+        let graph = safe_div(x.clone(), y.clone(), -1.0);
+        x.set(1.0);
+        assert_eq!(graph.compute(), -1.0);
+        y.set(4.0);
+        assert_eq!(graph.compute(), 0.25);
+        x.set(3.0);
+        assert_eq!(graph.compute(), 0.75);
+        y.set(0.0);
+        x.set(0.0);
+        assert_eq!(graph.compute(), -1.0);
+    }
+
+    #[test]
+    fn test_sub_sat() {
+        let x = create_input("x");
+        let y = create_input("y");
+        let graph = sub_sat(x.clone(), y.clone());
+        x.set(3.0);
+        y.set(5.0);
+        assert_eq!(graph.compute(), 0.0);
+        x.set(5.0);
+        y.set(3.0);
+        assert_eq!(graph.compute(), 2.0);
+        assert_eq!(graph.recompute_count(), 2);
+        y.set(4.5);
+        assert_eq!(graph.compute(), 0.5);
+    }
+
+    #[test]
+    fn test_silu() {
+        let x = create_input("x");
+        let graph = silu(x.clone());
+        assert_eq!(graph.compute(), 0.0);
+        x.set(20.0);
+        assert_eq!(round(graph.compute(), 4), 20.0);
+        x.set(-20.0);
+        assert_eq!(round(graph.compute(), 4), 0.0);
+        x.set(1.0);
+        assert_eq!(graph.compute(), sigmoid(x.clone()).compute());
+        assert_eq!(graph.recompute_count(), 4);
+        x.set(-100.0);
+        assert!(graph.compute().is_finite());
+    }
+
+    #[test]
+    fn test_gelu() {
+        let x = create_input("x");
+        let graph = gelu(x.clone());
+        assert_eq!(graph.compute(), 0.0);
+        x.set(10.0);
+        assert_eq!(round(graph.compute(), 4), 10.0);
+        x.set(-10.0);
+        assert_eq!(round(graph.compute(), 4), 0.0);
+        x.set(1.0);
+        assert_eq!(round(graph.compute(), 4), 0.8412);
+        assert_eq!(graph.recompute_count(), 4);
+    }
+
+    #[test]
+    fn test_activation() {
+        let x = create_input("x");
+        let kinds: [(ActivationKind, Rc<dyn Node<Output = f32>>); 5] = [
+            (ActivationKind::Relu, relu(x.clone())),
+            (ActivationKind::Sigmoid, sigmoid(x.clone())),
+            (ActivationKind::Tanh, tanh(x.clone())),
+            (ActivationKind::Silu, silu(x.clone())),
+            (ActivationKind::Gelu, gelu(x.clone())),
+        ];
+        for v in [-1.5, 0.7] {
+            x.set(v);
+            for (kind, helper) in &kinds {
+                let node = activation(x.clone(), *kind);
+                assert_eq!(node.compute(), helper.compute());
+                assert_eq!(node.label(), helper.label());
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_eval() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let s = sin(x1.clone());
+        let graph = add(s.clone(), mul(x2.clone(), x2.clone()));
+        let rows = vec![vec![0.0, 1.0], vec![0.0, 2.0], vec![1.0, 2.0]];
+        let outputs: Vec<f32> = stream_eval(&graph, &[x1.clone(), x2.clone()], rows.into_iter()).collect();
+        assert_eq!(outputs, vec![1.0, 4.0, 1f32.sin() + 4.0]);
+        // sin is not recomputed for the row with the same x1
+        assert_eq!(s.recompute_count(), 2);
+    }
+
+    #[test]
+    fn test_phase() {
+        use std::f32::consts::PI;
+        let re = create_input("re");
+        let im = create_input("im");
+        let graph = phase(re.clone(), im.clone());
+        im.set(1.0);
+        assert_eq!(graph.compute(), PI / 2.0);
+        re.set(-1.0);
+        im.set(0.0);
+        assert_eq!(graph.compute(), PI);
+        im.set(-1.0);
+        assert_eq!(graph.compute(), -3.0 * PI / 4.0);
+        assert_eq!(graph.recompute_count(), 3);
+    }
+
+    #[test]
+    fn test_slice_input() {
+        let buffer = Rc::new(RefCell::new(vec![1.0, 2.0, 3.0]));
+        let x = create_slice_input(buffer.clone(), 0, "x");
+        let y = create_slice_input(buffer.clone(), 2, "y");
+        let graph = mul(x.clone(), y.clone());
+        assert_eq!(graph.compute(), 3.0);
+        y.set(4.0);
+        assert_eq!(buffer.borrow()[2], 4.0);
+        assert_eq!(graph.compute(), 4.0);
+        buffer.borrow_mut().copy_from_slice(&[5.0, 6.0, 7.0]);
+        assert_eq!(graph.compute(), 4.0);
+        x.sync();
+        y.sync();
+        assert_eq!(graph.compute(), 35.0);
+        assert_eq!(graph.recompute_count(), 3);
+    }
+
+    #[test]
+    fn test_ema_time() {
+        let x = create_input("x");
+        let dt = create_input("dt");
+        let graph = ema_time(x.clone(), 1.0, dt.clone());
+        x.set(1.0);
+        dt.set(0.5);
+        assert_eq!(round(graph.compute(), 5), round(1.0 - (-0.5f32).exp(), 5));
+        // the longer step moves the average further toward the input
+        dt.set(2.0);
+        assert_eq!(round(graph.compute(), 5), round(1.0 - (-2.5f32).exp(), 5));
+        dt.set(0.25);
+        assert_eq!(round(graph.compute(), 5), round(1.0 - (-2.75f32).exp(), 5));
+    }
+
+    #[test]
+    fn test_detach() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let x3 = create_input("x3");
+        let sum = add(x1.clone(), x2.clone());
+        let graph = mul(sum.clone(), x3.clone());
+        x1.set(1.0);
+        x2.set(2.0);
+        x3.set(2.0);
+        assert_eq!(graph.compute(), 6.0);
+        let deps = detach(&sum);
+        x1.set(3.0);
+        x2.set(4.0);
+        assert_eq!(sum.compute(), 7.0);
+        assert_eq!(graph.compute(), 6.0);
+        assert_eq!(graph.recompute_count(), 1);
+        reattach(&sum, deps);
+        assert_eq!(graph.compute(), 14.0);
+        x1.set(0.0);
+        assert_eq!(graph.compute(), 8.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let x = create_input("x");
+        let exact = sin(x.clone());
+        let approx = create_input("approx");
+        let abs = abs_error(approx.clone(), exact.clone());
+        let rel = rel_error(approx.clone(), exact.clone());
+        x.set(std::f32::consts::FRAC_PI_2);
+        approx.set(1.5);
+        assert_eq!(abs.compute(), 0.5);
+        assert_eq!(rel.compute(), 0.5);
+        x.set(-std::f32::consts::FRAC_PI_2);
+        assert_eq!(abs.compute(), 2.5);
+        assert_eq!(rel.compute(), 2.5);
+        approx.set(-1.25);
+        assert_eq!(abs.compute(), 0.25);
+        assert_eq!(rel.compute(), 0.25);
+        x.set(0.0);
+        assert_eq!(rel.compute(), 1.25);
+    }
+
+    #[test]
+    fn test_without_cache() {
+        let x1 = create_input("x1");
+        let x2 = create_input("x2");
+        let cached = add(x1.clone(), x2.clone());
+        let uncached = add(x1.clone(), x2.clone()).without_cache();
+        x1.set(1.0);
+        x2.set(2.0);
+        for _ in 0..3 {
+            assert_eq!(cached.compute(), 3.0);
+            assert_eq!(uncached.compute(), 3.0);
+        }
+        assert_eq!(cached.recompute_count(), 1);
+        assert_eq!(uncached.recompute_count(), 3);
+    }
+
+    #[test]
+    fn test_signed_square() {
+        let x = create_input("x");
+        let graph = signed_square(x.clone());
+        x.set(-3.0);
+        assert_eq!(graph.compute(), -9.0);
+        x.set(2.0);
+        assert_eq!(graph.compute(), 4.0);
+    }
+
+    #[test]
+    fn test_map_range() {
+        let x = create_input("x");
+        let out_hi = create_input("out_hi");
+        let graph = map_range(x.clone(), constant(0.0), constant(10.0), constant(-1.0), out_hi.clone());
+        out_hi.set(1.0);
+        x.set(5.0);
+        assert_eq!(graph.compute(), 0.0);
+        x.set(7.5);
+        assert_eq!(graph.compute(), 0.5);
+        x.set(12.0);
+        assert_eq!(graph.compute(), 1.0);
+        x.set(-3.0);
+        assert_eq!(graph.compute(), -1.0);
+        out_hi.set(3.0);
+        x.set(5.0);
+        assert_eq!(graph.compute(), 1.0);
+    }
+
+    #[test]
+    fn test_integrate_time() {
+        let time = Rc::new(Cell::new(10.0));
+        let x = create_input("x");
+        x.set(2.0);
+        let graph = {
+            let time = time.clone();
+            integrate_time_with(x.clone(), move || time.get())
+        };
+        assert_eq!(graph.compute(), 0.0);
+        for dt in [0.25, 0.5, 0.25] {
+            time.set(time.get() + dt);
+            x.invalidate();
+            graph.compute();
+        }
+        assert_eq!(graph.compute(), 2.0);
+        x.set(-1.0);
+        time.set(12.0);
+        assert_eq!(graph.compute(), 1.0);
+    }
+
+    #[test]
+    fn test_logistic_map() {
+        let r = create_input("r");
+        r.set(3.2);
+        let graph = logistic_map(r.clone(), 0.5);
+        let mut x = 0.5f32;
+        for _ in 0..10 {
+            x = 3.2 * x * (1.0 - x);
+            assert_eq!(graph.compute(), x);
+            assert_eq!(graph.compute(), x);
+            graph.invalidate();
+        }
+    }
+
+    #[test]
+    fn test_safe_recip() {
+        let x = create_input("x");
+        let graph = safe_recip(x.clone(), 1e-3);
+        x.set(0.0);
+        assert_eq!(graph.compute(), 1.0 / 1e-3);
+        x.set(-1e-9);
+        assert_eq!(graph.compute(), -1.0 / 1e-3);
+        x.set(4.0);
+        assert_eq!(graph.compute(), 0.25);
+        x.set(-0.5);
+        assert_eq!(graph.compute(), -2.0);
+    }
+
+    #[test]
+    fn test_one_hot() {
+        let index = create_input("index");
+        let graph = one_hot(index.clone(), 4);
+        index.set(1.2);
+        assert_eq!(graph.compute(), vec![0.0, 1.0, 0.0, 0.0]);
+        index.set(2.6);
+        assert_eq!(graph.compute(), vec![0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(graph.recompute_count(), 2);
+        index.set(-1.0);
+        assert_eq!(graph.compute(), vec![0.0; 4]);
+        index.set(4.0);
+        assert_eq!(graph.compute(), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_norms() {
+        let v = create_input::<f32>("v");
+        let vector: Rc<dyn Node<Output = Vec<f32>>> =
+            Map::named("vector", v.clone(), |x| vec![3.0 * x, -4.0 * x]);
+        let l1 = l1_norm(vector.clone());
+        let l2 = l2_norm(vector.clone());
+        v.set(1.0);
+        assert_eq!(l1.compute(), 7.0);
+        assert_eq!(l2.compute(), 5.0);
+        v.set(-2.0);
+        assert_eq!(l1.compute(), 14.0);
+        assert_eq!(l2.compute(), 10.0);
+        let empty = l2_norm(Map::named("empty", v.clone(), |_| Vec::new()));
+        assert_eq!(empty.compute(), 0.0);
+    }
+
+    #[test]
+    fn test_wavetable() {
+        let phase = create_input("phase");
+        let table = vec![0.0, 1.0, 0.0, -1.0];
+        let linear = wavetable(phase.clone(), table.clone(), InterpMode::Linear);
+        let nearest = wavetable(phase.clone(), table, InterpMode::Nearest);
+        phase.set(0.125);
+        assert_eq!(linear.compute(), 0.5);
+        assert_eq!(nearest.compute(), 1.0);
+        // between the last and the first samples
+        phase.set(0.875);
+        assert_eq!(linear.compute(), -0.5);
+        assert_eq!(nearest.compute(), 0.0);
+        phase.set(-0.75);
+        assert_eq!(linear.compute(), 1.0);
+    }
+
+    #[test]
+    fn test_running_product() {
+        let x = create_input("x");
+        let graph = running_product(x.clone(), 1e6);
+        for v in [2.0, 1.5, -0.5] {
+            x.set(v);
+            graph.compute();
+        }
+        assert_eq!(graph.compute(), -1.5);
+        x.set(1e30);
+        assert_eq!(graph.compute(), -1e6);
+        x.set(-1e30);
+        assert_eq!(graph.compute(), 1e6);
+    }
+
+    #[test]
+    fn test_pid() {
+        let [setpoint, measurement, kp, ki, kd, dt] = ["sp", "m", "kp", "ki", "kd", "dt"].map(create_input);
+        let graph = pid(setpoint.clone(), measurement.clone(), kp.clone(), ki.clone(), kd.clone(), dt.clone());
+        kp.set(2.0);
+        dt.set(0.5);
+        setpoint.set(1.0);
+        assert_eq!(graph.compute(), 2.0);
+        measurement.set(0.75);
+        assert_eq!(graph.compute(), 0.5);
+        // the integral term accumulates the error 0.25 over the steps of 0.5
+        kp.set(0.0);
+        ki.set(1.0);
+        let mut prev = 0.0;
+        for _ in 0..3 {
+            graph.invalidate();
+            let out = graph.compute();
+            assert!(out > prev);
+            prev = out;
+        }
+        assert_eq!(prev, 0.5 + 0.125 + 3.0 * 0.125);
+    }
+
+    #[test]
+    fn test_leaky_integrator() {
+        let x = create_input("x");
+        let graph = leaky_integrator(x.clone(), 0.25);
+        x.set(2.0);
+        assert_eq!(graph.compute(), 2.0);
+        x.invalidate();
+        assert_eq!(graph.compute(), 3.5);
+        for _ in 0..100 {
+            x.invalidate();
+            graph.compute();
+        }
+        assert_eq!(round(graph.compute(), 4), 8.0);
+    }
+
+    #[test]
+    fn test_decay_schedule() {
+        let initial = create_input("initial");
+        let graph = decay_schedule(initial.clone(), 0.5);
+        initial.set(8.0);
+        let mut steps = Vec::new();
+        for _ in 0..5 {
+            steps.push(graph.compute());
+            initial.invalidate();
+        }
+        assert_eq!(steps, (0..5).map(|n| 8.0 * 0.5f32.powi(n)).collect::<Vec<_>>());
+        assert_eq!(steps[4], 0.5);
+    }
+
+    #[test]
+    fn test_crossing() {
+        let x = create_input("x");
+        let threshold = create_input("threshold");
+        threshold.set(1.0);
+        let graph = crossing(x.clone(), threshold.clone());
+        let series = [0.0, 0.5, 1.5, 2.0, 0.5, 0.0, 1.0];
+        let expected = [0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 1.0];
+        for (v, e) in series.iter().zip(expected) {
+            x.set(*v);
+            assert_eq!(graph.compute(), e);
+        }
+    }
+
+    #[test]
+    fn test_counter() {
+        let inc = create_input("inc");
+        let reset = create_input("reset");
+        let graph = counter(inc.clone(), reset.clone());
+        for v in [1.0, 2.0, 0.5] {
+            inc.set(v);
+            graph.compute();
+        }
+        assert_eq!(graph.compute(), 3.5);
+        reset.set(1.0);
+        assert_eq!(graph.compute(), 0.0);
+        reset.set(0.0);
+        assert_eq!(graph.compute(), 0.5);
+    }
+
+    #[test]
+    fn test_diff() {
+        let x = create_input("x");
+        let graph = diff(x.clone(), 0.0);
+        let mut v = 0.0;
+        for step in [0.5, 0.5, -2.0, 4.0] {
+            v += step;
+            x.set(v);
+            assert_eq!(graph.compute(), step);
+        }
+        let graph = diff(x.clone(), 2.0);
+        assert_eq!(graph.compute(), v - 2.0);
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        let x = create_input("x");
+        let graph = max_drawdown(x.clone());
+        let series = [10.0, 12.0, 9.0, 11.0, 15.0, 8.0, 10.0];
+        let expected = [0.0, 0.0, 3.0, 3.0, 3.0, 7.0, 7.0];
+        for (v, e) in series.iter().zip(expected) {
+            x.set(*v);
+            assert_eq!(graph.compute(), e);
+        }
+    }
+
+    #[test]
+    fn test_moving_median() {
+        let x = create_input("x");
+        let graph = moving_median(x.clone(), 5);
+        for v in [1.0, 2.0, 100.0, 3.0, 2.5] {
+            x.set(v);
+            graph.compute();
+        }
+        assert_eq!(graph.compute(), 2.5);
+        x.set(4.0);
+        assert_eq!(graph.compute(), 3.0);
+    }
+
+    #[test]
+    fn test_log_product() {
+        let x = create_input("x");
+        let log = log_product(x.clone());
+        let product = exp_f64(log.clone());
+        x.set(1e-3);
+        for _ in 0..50 {
+            x.invalidate();
+            log.compute();
+        }
+        assert!((product.compute() / 1e-150 - 1.0).abs() < 1e-3);
+        x.set(0.0);
+        assert_eq!(product.compute(), 0.0);
+        x.set(-1.0);
+        assert!(product.compute().is_nan());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let x = create_input("x");
+        let graph = histogram(x.clone(), vec![0.0, 1.0, 2.0, 4.0]);
+        for v in [0.5, 1.0, 3.9, 1.5, -1.0, 0.0, 4.0, 2.0, f32::NAN] {
+            x.set(v);
+            graph.compute();
+        }
+        assert_eq!(graph.compute(), vec![2.0, 2.0, 2.0]);
+        let states = state::dump_state(&graph);
+        x.set(0.5);
+        assert_eq!(graph.compute(), vec![3.0, 2.0, 2.0]);
+        state::load_state(&graph, &states);
+        x.set(3.0);
+        assert_eq!(graph.compute(), vec![2.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_running_correlation() {
+        let x = create_input("x");
+        let y = create_input("y");
+        let graph = running_correlation(x.clone(), y.clone());
+        x.set(1.0);
+        y.set(3.0);
+        // single pair has zero variance
+        assert_eq!(graph.compute(), 0.0);
+        for i in 2..10 {
+            x.set(i as f32);
+            y.set(2.0 * i as f32 + 1.0);
+            graph.compute();
+        }
+        assert_eq!(round(graph.compute(), 4), 1.0);
+        for i in 0..1000 {
+            x.set(i as f32);
+            y.set(-3.0 * i as f32);
+            graph.compute();
+        }
+        assert!(graph.compute() < -0.99);
+    }
+
+    #[test]
+    fn test_snap_to_set() {
+        let x = create_input("x");
+        let graph = snap_to_set(x.clone(), vec![1.0, 0.0, 0.5]);
+        x.set(0.3);
+        assert_eq!(graph.compute(), 0.5);
+        x.set(0.1);
+        assert_eq!(graph.compute(), 0.0);
+        x.set(-4.0);
+        assert_eq!(graph.compute(), 0.0);
+        x.set(7.0);
+        assert_eq!(graph.compute(), 1.0);
+        x.set(0.5);
+        assert_eq!(graph.compute(), 0.5);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let [x, lo, hi] = ["x", "lo", "hi"].map(create_input);
+        let graph = wrap(x.clone(), lo.clone(), hi.clone());
+        hi.set(5.0);
+        x.set(7.0);
+        assert_eq!(graph.compute(), 2.0);
+        x.set(-1.0);
+        assert_eq!(graph.compute(), 4.0);
+        lo.set(-2.0);
+        assert_eq!(graph.compute(), -1.0);
+        hi.set(-1.0);
+        assert_eq!(graph.compute(), -2.0);
+        hi.set(-3.0);
+        assert!(graph.compute().is_nan());
+    }
+
+    #[test]
+    fn test_bilinear() {
+        let [q00, q01, q10, q11, tx, ty] = ["q00", "q01", "q10", "q11", "tx", "ty"].map(create_input);
+        let graph = bilinear(q00.clone(), q01.clone(), q10.clone(), q11.clone(), tx.clone(), ty.clone());
+        for (q, v) in [(&q00, 1.0), (&q01, 2.0), (&q10, 4.0), (&q11, 8.0)] {
+            q.set(v);
+        }
+        for (x, y, v) in [(0.0, 0.0, 1.0), (0.0, 1.0, 2.0), (1.0, 0.0, 4.0), (1.0, 1.0, 8.0), (0.5, 0.5, 3.75)] {
+            tx.set(x);
+            ty.set(y);
+            assert_eq!(graph.compute(), v);
+        }
+    }
+
+    #[test]
+    fn test_soft_argmax() {
+        let xs: Vec<_> = (0..4).map(|_| create_input::<f32>("x")).collect();
+        let nodes = xs.iter().map(|x| x.clone() as Rc<dyn Node<Output = f32>>).collect();
+        let graph = soft_argmax(nodes, 0.01);
+        for (x, v) in xs.iter().zip([1.0, 2.0, 5.0, 0.5]) {
+            x.set(v);
+        }
+        assert!((graph.compute() - 2.0).abs() < 1e-4);
+        xs[0].set(10.0);
+        assert!(graph.compute().abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_softmax_entropy() {
+        let xs: Vec<_> = (0..4).map(|_| create_input::<f32>("x")).collect();
+        let nodes = xs.iter().map(|x| x.clone() as Rc<dyn Node<Output = f32>>).collect();
+        let graph = softmax_entropy(nodes, 1.0);
+        for x in &xs {
+            x.set(2.0);
+        }
+        assert_eq!(round(graph.compute(), 5), round(4f32.ln(), 5));
+        xs[2].set(100.0);
+        assert!(graph.compute() < 1e-6);
+        xs[2].set(2.5);
+        let entropy = graph.compute();
+        assert!(entropy > 0.0 && entropy < 4f32.ln());
+    }
+
+    #[test]
+    fn test_soft_min_max() {
+        let xs: Vec<_> = (0..3).map(|_| create_input::<f32>("x")).collect();
+        let nodes: Vec<Rc<dyn Node<Output = f32>>> = xs.iter().map(|x| x.clone() as _).collect();
+        for (x, v) in xs.iter().zip([1.0, -2.0, 3.0]) {
+            x.set(v);
+        }
+        for beta in [1.0, 10.0, 100.0] {
+            let (lo, hi) = (soft_min(nodes.clone(), beta).compute(), soft_max(nodes.clone(), beta).compute());
+            assert!(lo <= -2.0 && hi >= 3.0);
+            assert!(-2.0 - lo < 2.0 / beta && hi - 3.0 < 2.0 / beta);
+        }
+        // the gradient changes smoothly when the maximum switches between the values
+        let graph = soft_max(nodes[..2].to_vec(), 1.0);
+        xs[1].set(0.0);
+        let grads: Vec<f32> = [-0.1, 0.0, 0.1].iter().map(|&v| {
+            xs[0].set(v);
+            analysis::numeric_grad(&graph, &xs[0])
+        }).collect();
+        assert_eq!(round(grads[1], 3), 0.5);
+        assert!(grads[0] < grads[1] && grads[1] < grads[2] && grads[2] - grads[0] < 0.1);
+    }
+
+    #[test]
+    fn test_with_seed() {
+        let x = create_input("x");
+        let graph = add(x.clone(), hash_noise(x.clone()));
+        x.set(1.5);
+        let first = with_seed(42, || graph.compute());
+        assert_eq!(with_seed(42, || graph.compute()), first);
+        assert_ne!(with_seed(7, || graph.compute()), first);
+        assert!((1.5..2.5).contains(&first));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_seed(7, || -> f32 { panic!("query failed") })
+        }));
+        assert!(res.is_err());
+        assert_eq!(SEED.with(|s| s.get()), 0);
+    }
 }